@@ -1,20 +1,29 @@
 use crate::{
-    apply_velocity, coords, element_menu::ElementMenu, world::World, Pen, GRAVITY_PERIOD,
-    PLAY_AREA_PIXEL_HEIGHT, REACTION_PERIOD, TILE_PIXELS, UPDATES_PER_FRAME, WORLD_SIZE,
+    coords,
+    element_menu::ElementMenu,
+    in_bounds, point, update_world,
+    world::{Boundary, FieldId, World},
+    Pen, PLAY_AREA_PIXEL_HEIGHT, TILE_PIXELS, UPDATES_PER_FRAME, WORLD_HEIGHT, WORLD_SIZE,
+    WORLD_WIDTH,
 };
 use opengl_graphics::GlGraphics;
-use piston::{Button, ButtonArgs, ButtonState, MouseButton, RenderArgs, UpdateArgs};
+use piston::{Button, ButtonArgs, ButtonState, Key, MouseButton, RenderArgs, UpdateArgs};
 use std::collections::VecDeque;
 
+/// How far the up and down arrow keys move the ambient temperature
+const AMBIENT_TEMPERATURE_STEP: i16 = 10;
+
 pub struct App {
     gl: GlGraphics,
     turn: i32,
     world: World,
     element_menu: ElementMenu,
-    motion_queue: VecDeque<(usize, usize)>,
+    motion_queue: VecDeque<(usize, (i32, i32))>,
     selected_pen: Box<dyn Pen>,
     drawing: bool,
     last_mouse_pos: (f64, f64),
+    /// Scalar field drawn over the world, if any
+    overlay: Option<FieldId>,
 }
 
 impl App {
@@ -33,6 +42,7 @@ impl App {
             selected_pen,
             drawing: false,
             last_mouse_pos: (-1.0, -1.0),
+            overlay: None,
         }
     }
     pub fn render(&mut self, args: &RenderArgs) {
@@ -45,6 +55,7 @@ impl App {
         const BLACK: [f32; 4] = [0.0, 0.0, 0.0, 1.0];
 
         let world_ref = &self.world;
+        let overlay = self.overlay;
         self.gl.draw(args.viewport(), |c, gl| {
             // Clear the screen.
             clear(BLACK, gl);
@@ -68,6 +79,20 @@ impl App {
                     rectangle(*tile.color(), square, transform, gl);
                 }
             }
+            if let Some(field) = overlay {
+                for i in 0..WORLD_SIZE as usize {
+                    let color = world_ref.field_overlay_color(field, i);
+                    if color[3] > 0.0 {
+                        let (x, y) = coords(i);
+                        let square = rectangle::square(
+                            (x * TILE_PIXELS) as f64,
+                            (y * TILE_PIXELS) as f64,
+                            TILE_PIXELS as f64,
+                        );
+                        rectangle(color, square, transform, gl);
+                    }
+                }
+            }
         });
         let menu_ref = &mut self.element_menu;
         self.gl.draw(args.viewport(), |mut c, gl| {
@@ -81,15 +106,7 @@ impl App {
     pub fn update(&mut self, _args: &UpdateArgs) {
         let mut i = 0;
         while i < UPDATES_PER_FRAME {
-            self.world.pause_particles();
-            if self.turn % GRAVITY_PERIOD == 0 {
-                self.world.apply_gravity();
-            }
-            if self.turn % REACTION_PERIOD == 0 {
-                self.world.apply_periodic_reactions();
-                self.world.apply_thermal_diffusion();
-            }
-            apply_velocity(&mut self.world, &mut self.motion_queue);
+            update_world(&mut self.world, self.turn, &mut self.motion_queue);
             self.turn += 1;
             i += 1;
         }
@@ -104,6 +121,56 @@ impl App {
     }
 
     pub fn button(&mut self, args: &ButtonArgs) {
+        if let (Button::Keyboard(Key::B), ButtonState::Press) = (args.button, args.state) {
+            // Bind the tiles under the mouse into one rigid body
+            let (x, y) = (
+                (self.last_mouse_pos.0 / TILE_PIXELS as f64) as i32,
+                (self.last_mouse_pos.1 / TILE_PIXELS as f64) as i32,
+            );
+            if in_bounds(x, y) {
+                self.world.bind_connected(point(x, y));
+            }
+        }
+        if let (Button::Keyboard(Key::F), ButtonState::Press) = (args.button, args.state) {
+            // Cycle through drawing each scalar field over the world, and none
+            self.overlay = match self.overlay {
+                None if self.world.field_count() > 0 => Some(0),
+                Some(field) if field + 1 < self.world.field_count() => Some(field + 1),
+                _ => None,
+            };
+        }
+        if let (Button::Keyboard(key), ButtonState::Press) = (args.button, args.state) {
+            let settings = self.world.settings_mut();
+            match key {
+                Key::Up => settings.ambient_temperature += AMBIENT_TEMPERATURE_STEP,
+                Key::Down => settings.ambient_temperature -= AMBIENT_TEMPERATURE_STEP,
+                // Turn gravity a quarter turn anticlockwise or clockwise
+                Key::Left => {
+                    let (x, y) = settings.gravity_direction;
+                    settings.gravity_direction = (y, -x);
+                }
+                Key::Right => {
+                    let (x, y) = settings.gravity_direction;
+                    settings.gravity_direction = (-y, x);
+                }
+                // Switch between falling in one direction and towards the middle of the world
+                Key::G => {
+                    settings.gravity_center = match settings.gravity_center {
+                        Some(_) => None,
+                        None => Some((WORLD_WIDTH / 2, WORLD_HEIGHT / 2)),
+                    }
+                }
+                // Cycle through what happens at the edges of the world
+                Key::E => {
+                    settings.boundary = match settings.boundary {
+                        Boundary::Solid => Boundary::Open,
+                        Boundary::Open => Boundary::Wrap,
+                        Boundary::Wrap => Boundary::Solid,
+                    }
+                }
+                _ => {}
+            }
+        }
         if let Button::Mouse(MouseButton::Left) = args.button {
             match args.state {
                 ButtonState::Press => {
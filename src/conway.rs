@@ -1,5 +1,5 @@
-use crate::element::{Element, PeriodicReaction, FIXED};
-use crate::metal::{CHARGED_HEAD, METAL, NEUTRAL};
+use crate::element::{Channel, Element, PeriodicReaction, FIXED, RESTLESS};
+use crate::metal::CHARGED_HEAD;
 use crate::simple_elements::ELEMENT_DEFAULT;
 
 const DEAD: u8 = 1;
@@ -7,10 +7,11 @@ const ALIVE: u8 = 2;
 
 #[allow(dead_code)]
 pub static CONWAY: Element = Element {
-    flags: FIXED,
+    flags: FIXED | RESTLESS,
     color: [0.0, 0.0, 0.0, 1.0],
     mass: 10,
     id: 13,
+    restitution: 1.0,
     state_colors: Some(|state| {
         if state == ALIVE {
             &[0.0, 1.0, 0.0, 1.0]
@@ -42,8 +43,8 @@ pub static CONWAY: Element = Element {
                 this.edit_state(CONWAY.id(), DEAD);
                 for i in world.neighbors() {
                     if let Some(tile) = &mut world[i] {
-                        if tile.has_state(METAL.id(), NEUTRAL) {
-                            tile.edit_state(METAL.id(), CHARGED_HEAD)
+                        if tile.is_neutral_metal() {
+                            tile.set_channel(Channel::Charge, CHARGED_HEAD)
                         }
                     }
                 }
@@ -1,5 +1,5 @@
 use crate::{
-    element::{Color, Element, ElementSetup, PeriodicReaction, GRAVITY},
+    element::{Channel, Color, Element, ElementSetup, PeriodicReaction, GRAVITY},
     simple_elements::ELEMENT_DEFAULT,
     tile::Tile,
     water::WATER,
@@ -16,6 +16,11 @@ pub static DIRT: Element = Element {
     color: [1.0, 1.0, 0.5, 1.0],
     mass: 10,
     id: 18,
+    angle_of_repose: 45.0,
+    channels: &[Channel::Moisture],
+    state_channel: Some(Channel::Moisture),
+    // Wet dirt clumps together, and holds much steeper slopes
+    state_angles_of_repose: Some(|moisture| 45.0 + 30.0 * moisture as f64 / 255.0),
     state_colors: Some(|moisture| {
         if moisture == 0 {
             &DRY_COLOR
@@ -30,14 +35,13 @@ pub static DIRT: Element = Element {
         }
     }),
     periodic_reaction: PeriodicReaction::Some(|mut this, mut world| {
-        world.for_each_neighbor(|neighbor| match neighbor {
-            Some(tile) => {
+        world.for_each_neighbor(|neighbor| {
+            if let Some(tile) = neighbor {
                 if dirt_moisture(tile) > dirt_moisture(&this).saturating_add(5) {
-                    tile.adjust_info(-1);
-                    this.adjust_info(1);
+                    tile.adjust_channel(Channel::Moisture, -1);
+                    this.adjust_channel(Channel::Moisture, 1);
                 }
             }
-            None => (),
         });
         Some(this)
     }),
@@ -46,7 +50,7 @@ pub static DIRT: Element = Element {
 
 pub fn dirt_moisture(tile: &Tile) -> u8 {
     if tile.element_id() == DIRT.id {
-        tile.special_info()
+        tile.channel(Channel::Moisture)
     } else {
         0
     }
@@ -57,8 +61,8 @@ pub struct DirtSetup;
 impl ElementSetup for DirtSetup {
     fn register_reactions(&self, world: &mut crate::world::World) {
         world.register_collision_reaction(&WATER, &DIRT, |water, mut dirt| {
-            if dirt.special_info() <= 192 {
-                dirt.adjust_info(64);
+            if dirt.channel(Channel::Moisture) <= 192 {
+                dirt.adjust_channel(Channel::Moisture, 64);
                 (None, Some(dirt))
             } else {
                 (Some(water), Some(dirt))
@@ -1,3 +1,4 @@
+use crate::temperature::Temperature;
 use crate::tile::Tile;
 use crate::world::World;
 use crate::world_view::NeighborhoodView;
@@ -16,12 +17,63 @@ impl ElementId {
 // Can't use bitflags crate at the moment, since we need FLAG1 | FLAG2 to be const
 pub type EFlag = u8;
 
+/// Integer type of element masses
+#[cfg(not(feature = "wide-physics"))]
+pub type Mass = i8;
+#[cfg(feature = "wide-physics")]
+pub type Mass = i16;
+
 pub const NO_FLAGS: EFlag = 0;
 pub const GRAVITY: EFlag = 1 << 0;
 pub const FIXED: EFlag = 1 << 1;
 pub const PAUSE_EXEMPT: EFlag = 1 << 2;
-pub const PERFECT_RESTITUTION: EFlag = 1 << 3;
+/// Has a periodic reaction that can change things even when nothing around
+/// the tile does, such as random decay, so its chunk of the world never sleeps
+pub const RESTLESS: EFlag = 1 << 3;
 pub const FLUID: EFlag = 1 << 4;
+pub const GASEOUS: EFlag = 1 << 5;
+pub const BUOYANT: EFlag = 1 << 6;
+
+/// A value each tile keeps alongside its special info, for state that isn't
+/// tied to a single element, such as how wet a tile is. Elements declare the
+/// channels they use in `Element::channels`.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Channel {
+    /// How much water the tile has soaked up
+    Moisture,
+    /// Electric charge, such as the pulses that run along metal
+    Charge,
+    /// How many times the tile has aged, for elements that decay
+    Age,
+    /// Sap a plant has drawn up, which it grows with
+    Sap,
+    /// Gas packed into a gas tile on top of the tile's worth it always holds,
+    /// in `COMPRESSION_PER_TILE`ths of a tile
+    Compression,
+    /// Free for an element to use however it likes
+    Custom,
+}
+
+pub const CHANNEL_COUNT: usize = 6;
+
+/// Units of `Channel::Compression` that pack one more tile's worth of gas
+/// into a tile
+pub const COMPRESSION_PER_TILE: u8 = 32;
+
+impl Channel {
+    pub const ALL: [Channel; CHANNEL_COUNT] = [
+        Channel::Moisture,
+        Channel::Charge,
+        Channel::Age,
+        Channel::Sap,
+        Channel::Compression,
+        Channel::Custom,
+    ];
+
+    pub fn index(self) -> usize {
+        self as usize
+    }
+}
 
 impl SpecialElementInfo {
     pub fn none() -> Self {
@@ -33,7 +85,7 @@ impl SpecialElementInfo {
     }
 
     pub fn as_u8(self) -> u8 {
-        self.0.get() as u8
+        self.0.get()
     }
 }
 
@@ -87,15 +139,64 @@ pub enum PeriodicReaction {
     },
 }
 
+/// A change of phase that happens once a tile crosses `temperature`
+/// and has absorbed (or released) the latent heat difference between
+/// its element and `element`.
+#[derive(Clone, Copy)]
+pub struct PhaseChange {
+    pub temperature: Temperature,
+    pub element: &'static Element,
+}
+
 #[derive(Clone)]
 pub struct Element {
     pub flags: EFlag,
     pub color: Color,
-    pub mass: i8,
+    /// Mass of one tile. Every tile has the same volume,
+    /// so this is also the element's density.
+    pub mass: Mass,
     pub id: u8,
     pub periodic_reaction: PeriodicReaction,
     pub state_colors: Option<fn(u8) -> &'static Color>,
-    pub default_temperature: i16,
+    /// Channels of per-tile data this element keeps. A tile that turns into
+    /// this element loses any other channels it had.
+    pub channels: &'static [Channel],
+    /// Channel `state_colors` and `state_angles_of_repose` are looked up by,
+    /// instead of the special info
+    pub state_channel: Option<Channel>,
+    pub default_temperature: Temperature,
+    /// Energy needed to raise one tile of this element by one degree
+    pub heat_capacity: i32,
+    /// How readily heat flows through this element, from 0.0 (perfect
+    /// insulator) to 1.0 (the fastest rate the simulation allows)
+    pub thermal_conductivity: f64,
+    /// Energy held by this phase on top of `heat_capacity * temperature`.
+    /// The gap between two phases at their transition temperature
+    /// is the latent heat of that change.
+    pub latent_heat: i32,
+    /// Phase entered when heated to or past the given temperature
+    pub phase_above: Option<PhaseChange>,
+    /// Phase entered when cooled below the given temperature
+    pub phase_below: Option<PhaseChange>,
+    /// Steepest slope, in degrees, that a pile of this element holds
+    /// before tiles slide off it. 0.0 for elements that don't pile up.
+    pub angle_of_repose: f64,
+    pub state_angles_of_repose: Option<fn(u8) -> f64>,
+    /// Difference in gas pressure across a tile that blows it out of place
+    pub burst_pressure: f64,
+    /// Speed of the wind this element blows into the cell above it, like a fan
+    pub updraft: f64,
+    /// Fraction of its speed into a surface that a tile keeps when bouncing
+    /// off it, from 0.0 (doesn't bounce) to 1.0 (perfectly elastic)
+    pub restitution: f64,
+    /// Like `restitution`, but for bouncing off another loose tile,
+    /// which gives a little as it's struck
+    pub loose_restitution: f64,
+    /// Fraction of the speed along a surface that a tile loses when it touches
+    /// it, from 0.0 (perfectly slippery) to 1.0 (sticks where it lands).
+    /// Also the chance each gravity pass that a tile touching something fixed
+    /// in place holds on to it.
+    pub friction: f64,
 }
 
 impl Element {
@@ -103,14 +204,43 @@ impl Element {
         flag & self.flags != 0
     }
 
+    pub fn has_channel(&self, channel: Channel) -> bool {
+        self.channels.contains(&channel)
+    }
+
     pub const fn id(&self) -> ElementId {
         ElementId(self.id)
     }
 
+    /// Restitution of a contact between this element and `other`:
+    /// a bounce is only as lively as the less bouncy of the two
+    pub fn contact_restitution(&self, other: &Element) -> f64 {
+        self.restitution.min(other.restitution)
+    }
+
+    /// Restitution of a contact between this element and `other`
+    /// when neither is fixed in place
+    pub fn loose_contact_restitution(&self, other: &Element) -> f64 {
+        self.loose_restitution.min(other.loose_restitution)
+    }
+
+    /// Friction of a contact between this element and `other`,
+    /// which is as rough as the rougher of the two
+    pub fn contact_friction(&self, other: &Element) -> f64 {
+        self.friction.max(other.friction)
+    }
+
     pub fn get_color(&self, special_info: u8) -> &[f32; 4] {
         match self.state_colors {
             Some(function) => function(special_info),
             None => &self.color,
         }
     }
+
+    pub fn get_angle_of_repose(&self, special_info: u8) -> f64 {
+        match self.state_angles_of_repose {
+            Some(function) => function(special_info),
+            None => self.angle_of_repose,
+        }
+    }
 }
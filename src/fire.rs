@@ -1,8 +1,12 @@
-use crate::element::{Element, ElementId, ElementSetup, PeriodicReaction, GRAVITY, NO_FLAGS};
+use crate::element::{
+    Channel, Element, ElementId, ElementSetup, PeriodicReaction, BUOYANT, GASEOUS, GRAVITY,
+    PAUSE_EXEMPT, RESTLESS,
+};
 use crate::simple_elements::{ELEMENT_DEFAULT, SAND};
+use crate::temperature::Temperature;
 use crate::tile::{ElementState, Tile, Vector};
 use crate::water::{STEAM, WATER};
-use crate::world::World;
+use crate::world::{World, LIGHT};
 use rand::{thread_rng, Rng};
 
 #[allow(dead_code)]
@@ -15,24 +19,54 @@ pub static ASH: Element = Element {
     color: [0.1, 0.1, 0.1, 1.0],
     mass: 3,
     id: 5,
+    heat_capacity: 5,
+    thermal_conductivity: 0.1,
+    angle_of_repose: 40.0,
+    ..ELEMENT_DEFAULT
+};
+
+pub static SMOKE: Element = Element {
+    flags: GASEOUS | BUOYANT | PAUSE_EXEMPT | RESTLESS,
+    color: [0.4, 0.4, 0.4, 1.0],
+    mass: 1,
+    id: 23,
+    heat_capacity: 3,
+    thermal_conductivity: 0.1,
+    periodic_reaction: PeriodicReaction::Some(|this, mut world| {
+        // Slowly thins out into the air
+        if world.rng().gen_range(0, 400) == 0 {
+            None
+        } else {
+            Some(this)
+        }
+    }),
     ..ELEMENT_DEFAULT
 };
 
 pub static FIRE: Element = Element {
-    flags: NO_FLAGS,
+    flags: RESTLESS,
     color: [1.0, 0.0, 0.0, 1.0],
     mass: 3,
     id: 4,
-    periodic_reaction: PeriodicReaction::Some(|mut this, _world| {
-        if this.temperature < 300 || thread_rng().gen_range(0, 200) == 0 {
+    heat_capacity: 3,
+    thermal_conductivity: 0.5,
+    channels: &[Channel::Moisture],
+    periodic_reaction: PeriodicReaction::Some(|mut this, mut world| {
+        *world.field_mut(LIGHT) = 1.0;
+        // Dries out whatever was burning
+        this.adjust_channel(Channel::Moisture, -10);
+        if this.temperature < Temperature::celsius(300) || world.rng().gen_range(0, 200) == 0 {
             return {
-                if thread_rng().gen_range(0, 3) == 0 {
+                if world.rng().gen_range(0, 3) == 0 {
                     match this.special_info() {
                         MAKES_ASH => this.set_element(ASH.id()),
                         MAKES_WATER => this.set_element(WATER.id()),
                         _ => {}
                     }
                     Some(this)
+                } else if world.rng().gen_bool(0.5) {
+                    this.set_element(SMOKE.id());
+                    Some(this)
                 } else {
                     None
                 }
@@ -41,7 +75,7 @@ pub static FIRE: Element = Element {
 
         Some(this)
     }),
-    default_temperature: 500,
+    default_temperature: Temperature::celsius(500),
     ..ELEMENT_DEFAULT
 };
 
@@ -66,7 +100,7 @@ impl ElementSetup for FireElementSetup {
                             x: rng.gen_range(-10, 10),
                             y: rng.gen_range(-10, 10),
                         },
-                        (sand.temperature + fire.temperature) / 2,
+                        sand.temperature.midpoint(fire.temperature),
                     ));
                 }
             });
@@ -1,29 +1,38 @@
 use rand::{thread_rng, Rng};
 
-use crate::element::{Element, ElementId, ElementSetup, PAUSE_EXEMPT, PERFECT_RESTITUTION};
+use crate::element::{Channel, Element, ElementId, ElementSetup, GASEOUS, PAUSE_EXEMPT};
 use crate::fire::{BURNS_CLEAN, FIRE, MAKES_WATER};
 use crate::simple_elements::ELEMENT_DEFAULT;
-use crate::tile::{ElementState, Tile};
+use crate::tile::{ElementState, Speed, Tile};
 use crate::water::WATER;
-use crate::world::World;
+use crate::world::{Wind, World};
 use crate::Vector;
 
-const EXPLOSION_VELOCITY: i8 = 50;
-const ADJ_VEL: i8 = EXPLOSION_VELOCITY;
-const DIAG_VEL: i8 = ((EXPLOSION_VELOCITY as f64) * 1.414 / 2.0) as i8; // i.e. times sqrt(2)/2
+#[cfg(not(feature = "wide-physics"))]
+const EXPLOSION_VELOCITY: Speed = 50;
+// Wide velocities can fling tiles several cells in a single tick
+#[cfg(feature = "wide-physics")]
+const EXPLOSION_VELOCITY: Speed = 400;
+const ADJ_VEL: Speed = EXPLOSION_VELOCITY;
+const DIAG_VEL: Speed = ((EXPLOSION_VELOCITY as f64) * 1.414 / 2.0) as Speed; // i.e. times sqrt(2)/2
 
 #[rustfmt::skip]
-const EXPLOSION_VECTORS: [(i8, i8); 8] = [
+const EXPLOSION_VECTORS: [(Speed, Speed); 8] = [
     (-DIAG_VEL, -DIAG_VEL), (0, -ADJ_VEL), (DIAG_VEL, -DIAG_VEL),
     (-ADJ_VEL, 0),          /*No center*/  (ADJ_VEL, 0),
     (-DIAG_VEL, DIAG_VEL),  (0, ADJ_VEL),  (DIAG_VEL, DIAG_VEL),
 ];
 
 pub static GAS: Element = Element {
-    flags: PAUSE_EXEMPT | PERFECT_RESTITUTION,
+    flags: PAUSE_EXEMPT | GASEOUS,
     color: [1.0, 0.5, 1.0, 1.0],
     mass: 3,
     id: 3,
+    heat_capacity: 3,
+    thermal_conductivity: 0.1,
+    restitution: 1.0,
+    loose_restitution: 1.0,
+    channels: &[Channel::Compression],
     ..ELEMENT_DEFAULT
 };
 
@@ -42,12 +51,20 @@ impl ElementSetup for GasSetup {
                         },
                         Vector { x: 0, y: 0 },
                         Vector { x: 0, y: 0 },
-                        (gas.temperature + fire.temperature) / 2,
+                        gas.temperature.midpoint(fire.temperature),
                     ),
                 };
                 new_tile.velocity.x = new_tile.velocity.x.saturating_add(delta_v.0);
-                new_tile.velocity.y = new_tile.velocity.x.saturating_add(delta_v.1);
+                new_tile.velocity.y = new_tile.velocity.y.saturating_add(delta_v.1);
                 world[j] = Some(new_tile);
+                // The blast carries on outwards through the air, pushing loose tiles away
+                world.blow(
+                    j,
+                    Wind {
+                        x: delta_v.0 as f64,
+                        y: delta_v.1 as f64,
+                    },
+                );
             }
             gas.set_element(FIRE.id());
             (Some(gas), Some(fire))
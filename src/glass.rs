@@ -11,6 +11,9 @@ pub static GLASS: Element = Element {
     color: LIGHTEST_BLUE,
     mass: 10,
     id: 10,
+    thermal_conductivity: 0.05,
+    burst_pressure: 1.5,
+    restitution: 1.0,
     periodic_reaction: PeriodicReaction::Some(|mut this, world| {
         for j in world.neighbors() {
             if world[j]
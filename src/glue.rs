@@ -1,43 +1,29 @@
-use crate::element::{Element, ElementId, ElementSetup, PeriodicReaction, FIXED, FLUID, GRAVITY};
+use crate::element::{Channel, Element, PeriodicReaction, FIXED, FLUID, GRAVITY};
 use crate::simple_elements::ELEMENT_DEFAULT;
-use crate::world::World;
 
+/// Sticks to anything fixed in place that it touches, through friction
 pub static GLUE: Element = Element {
     flags: GRAVITY | FLUID,
     color: [0.9, 0.9, 0.5, 1.0],
     mass: 10,
     id: 15,
+    restitution: 0.0,
+    loose_restitution: 0.0,
+    friction: 1.0,
     ..ELEMENT_DEFAULT
 };
 
-pub struct GlueSetup;
-impl ElementSetup for GlueSetup {
-    fn register_reactions(&self, world: &mut World) {
-        world.register_flag_collision_reaction(&GLUE, FIXED, |mut glue_tile, fixed_tile| {
-            glue_tile.velocity.x = 0;
-            glue_tile.velocity.y = 0;
-            glue_tile.set_element(SOLID_GLUE.id());
-            (Some(glue_tile), Some(fixed_tile))
-        })
-    }
-
-    fn build_element(&self) -> Element {
-        GLUE.clone()
-    }
-
-    fn get_id(&self) -> ElementId {
-        GLUE.id()
-    }
-}
-
 pub static SOLID_GLUE: Element = Element {
     flags: FIXED,
     color: [0.8, 0.8, 0.7, 1.0],
     mass: 10,
     id: 14,
+    restitution: 0.0,
+    friction: 1.0,
+    channels: &[Channel::Age],
     periodic_reaction: PeriodicReaction::DecayInto {
         element_id: GLUE.id(),
-        lifetime: 10,
+        lifetime: 9,
         rarity: 100,
     },
     ..ELEMENT_DEFAULT
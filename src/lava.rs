@@ -1,17 +1,19 @@
-use crate::element::{PeriodicReaction, GRAVITY, PAUSE_EXEMPT};
+use crate::element::{PeriodicReaction, GRAVITY, PAUSE_EXEMPT, RESTLESS};
 use crate::fire::{BURNS_CLEAN, FIRE};
 use crate::simple_elements::ELEMENT_DEFAULT;
+use crate::temperature::Temperature;
 use crate::tile::{ElementState, Tile, Vector};
-use crate::world::World;
+use crate::world::{World, LIGHT};
 use crate::{Element, ElementId, ElementSetup, ROCK};
 use rand::Rng;
 
 pub static LAVA: Element = Element {
-    flags: GRAVITY | PAUSE_EXEMPT,
+    flags: GRAVITY | PAUSE_EXEMPT | RESTLESS,
     color: [0.8, 0.5, 0.2, 1.0],
     mass: 50,
     id: 9,
     periodic_reaction: PeriodicReaction::Some(|mut this, mut world| {
+        *world.field_mut(LIGHT) = 0.8;
         for i in world.neighbors() {
             match world[i] {
                 Some(_) => {
@@ -19,19 +21,19 @@ pub static LAVA: Element = Element {
                 }
                 None => {
                     // Sprinkle fire into empty ones
-                    if rand::thread_rng().gen_range(0, 150) == 0 {
+                    if world.rng().gen_range(0, 150) == 0 {
                         world[i] = Some(Tile::new(
                             ElementState::new(FIRE.id(), BURNS_CLEAN),
                             Vector {
-                                x: rand::thread_rng().gen_range(-126, 127),
-                                y: rand::thread_rng().gen_range(-126, 127),
+                                x: world.rng().gen_range(-126, 127),
+                                y: world.rng().gen_range(-126, 127),
                             },
                             Vector {
-                                x: rand::thread_rng().gen_range(
+                                x: world.rng().gen_range(
                                     this.velocity.x.saturating_sub(10),
                                     this.velocity.x.saturating_add(10),
                                 ),
-                                y: rand::thread_rng().gen_range(
+                                y: world.rng().gen_range(
                                     this.velocity.y.saturating_sub(10),
                                     this.velocity.y.saturating_add(10),
                                 ),
@@ -39,7 +41,7 @@ pub static LAVA: Element = Element {
                             this.temperature,
                         ));
                         // Every time you create fire, roll to cool into rock
-                        if this.temperature < 800 {
+                        if this.temperature < Temperature::celsius(800) {
                             this.set_element(ROCK.id())
                         }
                     }
@@ -48,7 +50,7 @@ pub static LAVA: Element = Element {
         }
         Some(this)
     }),
-    default_temperature: 1000,
+    default_temperature: Temperature::celsius(1000),
     ..ELEMENT_DEFAULT
 };
 
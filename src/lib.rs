@@ -14,6 +14,7 @@ mod oil;
 mod plant;
 mod simple_elements;
 mod snow;
+mod temperature;
 mod tile;
 mod util;
 mod water;
@@ -23,20 +24,23 @@ mod world_view;
 use crate::app::App;
 use crate::conway::CONWAY;
 use crate::dirt::DirtSetup;
-use crate::element::{Color, DefaultSetup, Element, ElementId, ElementSetup, FIXED};
+use crate::element::{
+    Channel, Color, DefaultSetup, Element, ElementId, ElementSetup, COMPRESSION_PER_TILE, FIXED,
+};
 use crate::element_menu::ElementMenu;
-use crate::fire::{FireElementSetup, ASH, FIRE};
+use crate::fire::{FireElementSetup, ASH, FIRE, SMOKE};
 use crate::gas::{GasSetup, GAS};
 use crate::glass::GLASS;
-use crate::glue::{GlueSetup, SOLID_GLUE};
+use crate::glue::{GLUE, SOLID_GLUE};
 use crate::lava::LavaSetup;
 use crate::metal::{ElectronSetup, LIQUID_METAL, METAL};
 use crate::oil::OIL;
 use crate::plant::{PLANT, ROOT, SEED};
-use crate::simple_elements::{ELEMENT_DEFAULT, ROCK, SAND, WALL};
-use crate::tile::{ElementState, Tile, Vector};
+use crate::simple_elements::{ELEMENT_DEFAULT, FAN, ROCK, SAND, WALL};
+use crate::temperature::Temperature;
+use crate::tile::{ElementState, Speed, Tile, Vector};
 use crate::water::{STEAM, WATER};
-use crate::world::World;
+use crate::world::{Boundary, World};
 use itertools::{iproduct, Itertools};
 use lazy_static::{self as lazy_static_crate, lazy_static};
 use rand::{thread_rng, Rng};
@@ -63,7 +67,7 @@ lazy_static! {
             Box::new(SnowSetup),
             default_setup(&OIL),
             default_setup(&CONWAY),
-            Box::new(GlueSetup),
+            default_setup(&GLUE),
             default_setup(&SOLID_GLUE),
             default_setup(&STEAM),
             default_setup(&LIQUID_METAL),
@@ -71,6 +75,8 @@ lazy_static! {
             default_setup(&SEED),
             default_setup(&PLANT),
             default_setup(&ROOT),
+            default_setup(&FAN),
+            default_setup(&SMOKE),
         ]
     };
 }
@@ -101,7 +107,11 @@ const UPDATES_PER_FRAME: i32 = 20;
 // 1 second = 60 frames = 1200 updates
 const GRAVITY_PERIOD: i32 = 5;
 const REACTION_PERIOD: i32 = 3; // This is still fast! :D It used to be 100!
-const PAUSE_VELOCITY: i8 = 3;
+const PAUSE_VELOCITY: Speed = 3;
+/// Positions within a cell run from `MIN_POSITION` up to
+/// `MIN_POSITION + POSITIONS_PER_CELL - 1`, however wide `Speed` is
+const MIN_POSITION: i32 = -128;
+const POSITIONS_PER_CELL: i32 = 256;
 extern crate glutin_window;
 extern crate graphics;
 extern crate opengl_graphics;
@@ -115,7 +125,7 @@ use piston::input::{ButtonEvent, MouseCursorEvent, RenderEvent, UpdateEvent};
 use piston::window::WindowSettings;
 
 fn in_bounds(x: i32, y: i32) -> bool {
-    x >= 0 && x < WORLD_WIDTH && y >= 0 && y < WORLD_HEIGHT
+    (0..WORLD_WIDTH).contains(&x) && (0..WORLD_HEIGHT).contains(&y)
 }
 
 #[inline]
@@ -132,33 +142,6 @@ fn above(position: usize) -> Option<usize> {
         .filter(|x| x < &(WORLD_SIZE as usize))
 }
 
-#[inline]
-fn left(position: usize) -> Option<usize> {
-    position
-        .checked_sub(1)
-        .filter(|x| x < &(WORLD_SIZE as usize))
-}
-
-#[inline]
-fn right(position: usize) -> Option<usize> {
-    position
-        .checked_add(1)
-        .filter(|x| x < &(WORLD_SIZE as usize))
-}
-
-#[inline]
-fn adjacent_x(position1: usize, position2: usize) -> bool {
-    let is_left = match left(position1) {
-        None => false,
-        Some(x) => x == position2,
-    };
-    let is_right = match right(position1) {
-        None => false,
-        Some(x) => x == position2,
-    };
-    is_left || is_right
-}
-
 #[inline]
 #[allow(dead_code)]
 fn adjacent_y(position1: usize, position2: usize) -> bool {
@@ -178,55 +161,98 @@ pub fn neighbor_count(index: usize, predicate: impl Fn(usize) -> bool) -> usize
 }
 
 pub fn neighbors(index: usize) -> impl Iterator<Item = usize> + 'static {
-    let x = index as i32 % WORLD_WIDTH;
-    let y = index as i32 / WORLD_WIDTH;
-    iproduct!(-1i32..=1i32, -1i32..=1i32) // consider all adjacent tuples
-        .filter(|&tuple| tuple != (0, 0)) // exclude same tile
-        .map(move |(dx, dy)| (x + dx, y + dy))
-        .filter(|&(x, y)| in_bounds(x, y)) // exclude tiles outside world bounds
-        .map(|(x, y)| (x + y * WORLD_WIDTH) as usize) // calculate index
+    neighbors_within(index, Boundary::Solid)
 }
 
-pub fn raw_neighbors(index: usize) -> impl Iterator<Item = usize> + 'static {
-    let x = index as i32 % WORLD_WIDTH;
-    let y = index as i32 / WORLD_WIDTH;
+/// The cells around `index`, which wrap around to the far side of the world
+/// with a `Boundary::Wrap`, and otherwise stop at its edges
+pub fn neighbors_within(index: usize, boundary: Boundary) -> impl Iterator<Item = usize> + 'static {
     iproduct!(-1i32..=1i32, -1i32..=1i32) // consider all adjacent tuples
         .filter(|&tuple| tuple != (0, 0)) // exclude same tile
-        .map(move |(dx, dy)| (x + dx, y + dy)) // exclude tiles outside world bounds
-        .map(|(x, y)| (x + y * WORLD_WIDTH) as usize) // calculate index
+        .filter_map(move |step| offset(index, step, boundary)) // exclude tiles outside world bounds
+}
+
+/// The cell `(dx, dy)` away from `index`, if it is inside the world once
+/// `boundary` is taken into account
+pub fn offset(index: usize, (dx, dy): (i32, i32), boundary: Boundary) -> Option<usize> {
+    let (x, y) = coords(index);
+    cell_within(x + dx, y + dy, boundary)
+}
+
+/// Index of the cell at `(x, y)`, which with a `Boundary::Wrap` may be
+/// past the edge of the world and refer to a cell on the other side
+fn cell_within(x: i32, y: i32, boundary: Boundary) -> Option<usize> {
+    match boundary {
+        Boundary::Wrap => Some(point(x.rem_euclid(WORLD_WIDTH), y.rem_euclid(WORLD_HEIGHT))),
+        Boundary::Solid | Boundary::Open if in_bounds(x, y) => Some(point(x, y)),
+        Boundary::Solid | Boundary::Open => None,
+    }
+}
+
+/// The step from `source` to the neighboring cell `destination`,
+/// including steps that wrap around the edges of the world
+fn step_between(source: usize, destination: usize) -> (i32, i32) {
+    let (x1, y1) = coords(source);
+    let (x2, y2) = coords(destination);
+    let shortest = |delta: i32, size: i32| {
+        if delta > 1 {
+            delta - size
+        } else if delta < -1 {
+            delta + size
+        } else {
+            delta
+        }
+    };
+    (
+        shortest(x2 - x1, WORLD_WIDTH),
+        shortest(y2 - y1, WORLD_HEIGHT),
+    )
 }
 
-fn apply_velocity(world: &mut World, motion_queue: &mut VecDeque<(usize, usize)>) -> bool {
+fn apply_velocity(world: &mut World, motion_queue: &mut VecDeque<(usize, (i32, i32))>) -> bool {
+    let boundary = world.settings().boundary;
     let mut needs_update = false;
     // This makes more sense at the end, but borrow checker didn't like it
     // maybe check it later?
     motion_queue.clear();
-    for i in 0..WORLD_SIZE as usize {
+    for i in world.scan_cells() {
         if let Some(ref mut tile) = &mut world[i] {
             if
             /* !tile.paused && */
-            !tile.has_flag(FIXED) {
-                let (new_x, overflowed_x) = tile.position.x.overflowing_add(tile.velocity.x);
-                let (new_y, overflowed_y) = tile.position.y.overflowing_add(tile.velocity.y);
+            // Tiles of rigid bodies move with their body instead
+            !tile.has_flag(FIXED) && tile.body.is_none() {
+                // Whole cells the tile moves this tick, and where it ends up within the last one
+                let advance = |position: Speed, velocity: Speed| {
+                    let total = position as i32 + velocity as i32;
+                    let cells = (total - MIN_POSITION).div_euclid(POSITIONS_PER_CELL);
+                    (cells, (total - cells * POSITIONS_PER_CELL) as Speed)
+                };
+                let (delta_x, new_x) = advance(tile.position.x, tile.velocity.x);
+                let (delta_y, new_y) = advance(tile.position.y, tile.velocity.y);
                 tile.position.x = new_x;
                 tile.position.y = new_y;
-                if overflowed_x || overflowed_y {
+                if delta_x != 0 || delta_y != 0 {
                     needs_update = true;
-                    let delta_x = if overflowed_x {
-                        tile.velocity.x.signum()
-                    } else {
-                        0
-                    };
-                    let delta_y = if overflowed_y {
-                        tile.velocity.y.signum()
+                    let (old_grid_x, old_grid_y) = coords(i);
+                    let (delta_x, delta_y) = if boundary == Boundary::Solid {
+                        // Tiles fast enough to cross several cells stop at the edge of the
+                        // world, and bounce off it as they would off a wall
+                        let clamped_x =
+                            (old_grid_x + delta_x).clamp(0, WORLD_WIDTH - 1) - old_grid_x;
+                        let clamped_y =
+                            (old_grid_y + delta_y).clamp(0, WORLD_HEIGHT - 1) - old_grid_y;
+                        if clamped_x != delta_x {
+                            tile.reflect_velocity((delta_x.signum(), 0), &WALL);
+                        }
+                        if clamped_y != delta_y {
+                            tile.reflect_velocity((0, delta_y.signum()), &WALL);
+                        }
+                        (clamped_x, clamped_y)
                     } else {
-                        0
+                        (delta_x, delta_y)
                     };
-                    let (old_grid_x, old_grid_y) = coords(i);
-                    let (new_grid_x, new_grid_y) =
-                        (old_grid_x + delta_x as i32, old_grid_y + delta_y as i32);
-                    if in_bounds(new_grid_x, new_grid_y) {
-                        let swap_pair = (i, point(new_grid_x, new_grid_y));
+                    if (delta_x, delta_y) != (0, 0) {
+                        let swap_pair = (i, (delta_x, delta_y));
                         // this logic is to allow "trains" of adjacent particles
                         // to travel smoothly and not knock each other
                         if delta_y < 0 || (delta_y == 0 && delta_x < 0) {
@@ -240,13 +266,51 @@ fn apply_velocity(world: &mut World, motion_queue: &mut VecDeque<(usize, usize)>
         }
     }
 
-    for (i, j) in motion_queue {
-        world.move_particle(*i, *j);
+    for (i, delta) in motion_queue {
+        world.move_along(*i, *delta);
     }
 
     needs_update
 }
 
+/// Advances the world by a single update
+fn update_world(world: &mut World, turn: i32, motion_queue: &mut VecDeque<(usize, (i32, i32))>) {
+    world.begin_tick();
+    world.pause_particles();
+    if turn % GRAVITY_PERIOD == 0 {
+        world.apply_gravity();
+        world.apply_buoyancy();
+        world.apply_granular_sliding();
+        world.apply_liquid_flow();
+    }
+    if turn % REACTION_PERIOD == 0 {
+        world.apply_periodic_reactions();
+        world.apply_thermal_diffusion();
+        world.apply_field_diffusion();
+        world.apply_gas_pressure();
+        world.apply_air_flow();
+    }
+    apply_velocity(world, motion_queue);
+    world.apply_rigid_bodies();
+}
+
+/// Creates an empty world, with every element's collision reactions registered
+fn create_world() -> World {
+    // Prepare the list of elements and their setup structs
+    lazy_static_crate::initialize(&SETUPS);
+    lazy_static_crate::initialize(&ELEMENTS);
+    let elem_count = SETUPS.len();
+
+    // Create the world
+    let mut world = World::new(elem_count);
+
+    // Register each element's collision reactions based on setup structs
+    for s in SETUPS.iter() {
+        s.register_reactions(&mut world);
+    }
+    world
+}
+
 fn coords(i: usize) -> (i32, i32) {
     (
         (i % (WORLD_WIDTH as usize)) as i32,
@@ -275,15 +339,8 @@ impl Pen for DeletePen {
         for x in x - self.radius..=x + self.radius {
             for y in y - self.radius..=y + self.radius {
                 if in_bounds(x, y) {
-                    match &world[point(x, y)] {
-                        Some(tile) if tile.element_id() != WALL.id => {
-                            // Destroy non-wall tiles
-                            world[point(x, y)] = None
-                        }
-                        _ => {
-                            // Leave walls and empty tiles alone
-                        }
-                    }
+                    world[point(x, y)] = None;
+                    world.wake(point(x, y));
                 }
             }
         }
@@ -323,7 +380,17 @@ impl Pen for ElementPen {
                         Vector { x: 0, y: 0 },
                         velocity,
                         self.element.default_temperature, //false,
-                    ))
+                    ));
+                    world.wake(point(x, y));
+                } else if in_bounds(x, y) && self.element.has_channel(Channel::Compression) {
+                    // Drawing gas over the same gas packs more of it in
+                    if let Some(tile) = &mut world[point(x, y)] {
+                        if tile.element_id() == self.element.id {
+                            tile.adjust_channel(Channel::Compression, COMPRESSION_PER_TILE as i16);
+                            tile.save_state();
+                            world.wake(point(x, y));
+                        }
+                    }
                 }
             }
         }
@@ -339,18 +406,7 @@ impl Pen for ElementPen {
 }
 
 pub fn game_loop() {
-    // Prepare the list of elements and their setup structs
-    lazy_static_crate::initialize(&SETUPS);
-    lazy_static_crate::initialize(&ELEMENTS);
-    let elem_count = SETUPS.len();
-
-    // Create the world
-    let mut world = World::new(elem_count);
-
-    // Register each element's collision reactions based on setup structs
-    for s in SETUPS.iter() {
-        s.register_reactions(&mut world);
-    }
+    let mut world = create_world();
 
     // Draw walls around the edge of the playing area
     util::create_walls(&mut world);
@@ -400,3 +456,294 @@ pub fn game_loop() {
         }
     }
 }
+
+/// Fills a rectangle of the world with resting tiles at room temperature
+#[cfg(test)]
+fn fill(
+    world: &mut World,
+    element: &Element,
+    xs: std::ops::RangeInclusive<i32>,
+    ys: std::ops::RangeInclusive<i32>,
+) {
+    for (x, y) in iproduct!(xs, ys) {
+        world[point(x, y)] = Some(Tile::stationary(
+            ElementState::default(element.id()),
+            Temperature::celsius(20),
+        ));
+    }
+}
+
+/// Runs the given number of updates
+#[cfg(test)]
+fn run(world: &mut World, updates: i32) {
+    let mut motion_queue = VecDeque::new();
+    for turn in 0..updates {
+        update_world(world, turn, &mut motion_queue);
+    }
+}
+
+/// Average row of all tiles of the given element
+#[cfg(test)]
+fn average_height(world: &World, element: &Element) -> f64 {
+    let heights: Vec<i32> = (0..WORLD_SIZE as usize)
+        .filter(|&i| {
+            world
+                .state_at(i)
+                .is_some_and(|state| state.element_id == element.id())
+        })
+        .map(|i| coords(i).1)
+        .collect();
+    heights.iter().sum::<i32>() as f64 / heights.len() as f64
+}
+
+#[test]
+fn liquids_settle_into_layers_by_density() {
+    let mut world = create_world();
+    // A narrow tank, with the lighter oil poured in underneath the water
+    fill(&mut world, &WALL, 10..=10, 10..=60);
+    fill(&mut world, &WALL, 17..=17, 10..=60);
+    fill(&mut world, &WALL, 10..=17, 60..=60);
+    fill(&mut world, &WATER, 11..=16, 40..=49);
+    fill(&mut world, &OIL, 11..=16, 50..=59);
+    run(&mut world, 3000);
+    assert!(average_height(&world, &OIL) + 2.0 < average_height(&world, &WATER));
+}
+
+#[test]
+fn liquid_levels_out_between_connected_vessels() {
+    let mut world = create_world();
+    // Two vessels, joined by a one tile gap at the bottom of the wall between them
+    fill(&mut world, &WALL, 10..=10, 20..=60);
+    fill(&mut world, &WALL, 40..=40, 20..=60);
+    fill(&mut world, &WALL, 10..=40, 60..=60);
+    fill(&mut world, &WALL, 25..=25, 30..=58);
+    fill(&mut world, &WATER, 11..=24, 35..=59);
+    run(&mut world, 1000);
+
+    let surface = |xs: std::ops::RangeInclusive<i32>| {
+        iproduct!(xs, 20..60)
+            .filter(|&(x, y)| world.state_at(point(x, y)).is_some())
+            .map(|(_, y)| y)
+            .min()
+            .unwrap()
+    };
+    assert!((surface(11..=24) - surface(26..=39)).abs() <= 2);
+}
+
+/// Widths of the bases of the piles that columns of sand and of dirt as wet
+/// as `moisture`, both `width` tiles wide, slump into
+#[cfg(test)]
+fn sand_and_dirt_pile_widths(width: i32, moisture: u8) -> (usize, usize) {
+    use crate::dirt::DIRT;
+    use crate::element::Channel;
+
+    let mut world = create_world();
+    fill(&mut world, &WALL, 10..=190, 150..=150);
+    fill(&mut world, &SAND, 50..=49 + width, 70..=149);
+    fill(&mut world, &DIRT, 140..=139 + width, 70..=149);
+    for i in 0..WORLD_SIZE as usize {
+        if let Some(tile) = &mut world[i] {
+            if tile.element_id() == DIRT.id {
+                tile.set_channel(Channel::Moisture, moisture);
+                tile.save_state();
+            }
+        }
+    }
+    run(&mut world, 2000);
+
+    let pile_width = |xs: std::ops::RangeInclusive<i32>| {
+        xs.filter(|&x| world.state_at(point(x, 149)).is_some())
+            .count()
+    };
+    (pile_width(10..=100), pile_width(100..=190))
+}
+
+#[test]
+fn wet_dirt_holds_a_steeper_pile_than_sand() {
+    let (sand_width, dirt_width) = sand_and_dirt_pile_widths(4, 255);
+    // Sand should have slumped into a wide cone, and wet dirt much less so
+    assert!(sand_width > 20);
+    assert!(dirt_width + 10 < sand_width);
+}
+
+#[test]
+fn angles_of_repose_below_60_degrees_build_different_slopes() {
+    // Sand rests at 35 degrees, and dirt this damp at 55
+    let (sand_width, dirt_width) = sand_and_dirt_pile_widths(6, 85);
+    assert!(dirt_width + 4 < sand_width, "{} {}", sand_width, dirt_width);
+}
+
+#[test]
+fn smoke_collects_under_a_ceiling() {
+    let mut world = create_world();
+    // A closed box, with smoke along the bottom
+    fill(&mut world, &WALL, 10..=10, 10..=60);
+    fill(&mut world, &WALL, 30..=30, 10..=60);
+    fill(&mut world, &WALL, 10..=30, 10..=10);
+    fill(&mut world, &WALL, 10..=30, 60..=60);
+    fill(&mut world, &SMOKE, 11..=29, 55..=59);
+    run(&mut world, 300);
+    assert!(average_height(&world, &SMOKE) < 25.0);
+}
+
+#[test]
+fn diagonal_moves_do_not_squeeze_between_tiles() {
+    let mut world = create_world();
+    let mut motion_queue = VecDeque::new();
+    fill(&mut world, &WALL, 11..=11, 10..=10);
+    fill(&mut world, &WALL, 10..=10, 11..=11);
+    fill(&mut world, &ROCK, 10..=10, 10..=10);
+    fill(&mut world, &ROCK, 50..=50, 10..=10);
+    for &x in &[10, 50] {
+        world[point(x, 10)].as_mut().unwrap().velocity = Vector { x: 100, y: 100 };
+    }
+    for _ in 0..3 {
+        apply_velocity(&mut world, &mut motion_queue);
+    }
+    // Stopped by the walls on either side of the gap
+    assert!(world[point(11, 11)].is_none());
+    assert_eq!(world[point(10, 10)].as_ref().unwrap().element_id(), ROCK.id);
+    // But free to move diagonally through open space
+    assert!(world[point(50, 10)].is_none());
+    assert!(world[point(51, 11)].is_some());
+}
+
+#[test]
+fn tiles_blocked_at_a_corner_keep_moving_along_the_other_axis() {
+    let mut world = create_world();
+    let mut motion_queue = VecDeque::new();
+    fill(&mut world, &WALL, 11..=11, 10..=10);
+    fill(&mut world, &ROCK, 10..=10, 10..=10);
+    fill(&mut world, &WALL, 50..=50, 11..=11);
+    fill(&mut world, &ROCK, 50..=50, 10..=10);
+    world[point(10, 10)].as_mut().unwrap().velocity = Vector { x: 100, y: 100 };
+    world[point(50, 10)].as_mut().unwrap().velocity = Vector { x: 100, y: 100 };
+    for _ in 0..3 {
+        apply_velocity(&mut world, &mut motion_queue);
+    }
+    // Bounced off the wall beside it, and still fell
+    let rock = world[point(10, 11)].as_ref().unwrap();
+    assert_eq!(rock.element_id(), ROCK.id);
+    assert!(rock.velocity.x < 0);
+    // Bounced off the wall below it, and still moved to the right
+    let rock = world[point(51, 10)].as_ref().unwrap();
+    assert_eq!(rock.element_id(), ROCK.id);
+    assert!(rock.velocity.y < 0);
+}
+
+#[cfg(feature = "wide-physics")]
+#[test]
+fn fast_tiles_cross_several_cells_and_stop_at_walls() {
+    let mut world = create_world();
+    let mut motion_queue = VecDeque::new();
+    fill(&mut world, &ROCK, 10..=10, 10..=10);
+    fill(&mut world, &ROCK, 10..=10, 30..=30);
+    fill(&mut world, &WALL, 14..=14, 30..=30);
+    for &y in &[10, 30] {
+        world[point(10, y)].as_mut().unwrap().velocity = Vector { x: 1000, y: 0 };
+    }
+    apply_velocity(&mut world, &mut motion_queue);
+    // About four cells in one tick
+    assert!(world[point(14, 10)].is_some());
+    // Unless there's a wall in the way
+    assert!(world[point(13, 30)].is_some());
+    assert!(world[point(13, 30)].as_ref().unwrap().velocity.x < 0);
+}
+
+#[test]
+fn tiles_leave_an_open_world_and_wrap_around_a_toroidal_one() {
+    let mut world = create_world();
+    let mut motion_queue = VecDeque::new();
+    world.settings_mut().boundary = Boundary::Open;
+    fill(&mut world, &ROCK, 0..=0, 10..=10);
+    world[point(0, 10)].as_mut().unwrap().velocity = Vector { x: -100, y: 0 };
+    for _ in 0..5 {
+        apply_velocity(&mut world, &mut motion_queue);
+    }
+    assert!((0..WORLD_SIZE as usize).all(|i| world[i].is_none()));
+
+    world.settings_mut().boundary = Boundary::Wrap;
+    fill(&mut world, &ROCK, 0..=0, 10..=10);
+    world[point(0, 10)].as_mut().unwrap().velocity = Vector { x: -100, y: 0 };
+    for _ in 0..3 {
+        apply_velocity(&mut world, &mut motion_queue);
+    }
+    assert!(world[point(WORLD_WIDTH - 1, 10)].is_some());
+}
+
+#[test]
+fn tiles_bounce_off_the_edge_of_a_solid_world() {
+    let mut world = create_world();
+    let mut motion_queue = VecDeque::new();
+    fill(&mut world, &ROCK, 197..=197, 10..=10);
+    world[point(197, 10)].as_mut().unwrap().velocity = Vector { x: 100, y: 0 };
+    for _ in 0..10 {
+        apply_velocity(&mut world, &mut motion_queue);
+    }
+    let (i, tile) = (0..WORLD_SIZE as usize)
+        .find_map(|i| world[i].as_ref().map(|tile| (i, tile)))
+        .unwrap();
+    assert!(tile.velocity.x < 0);
+    assert!(coords(i).0 < WORLD_WIDTH - 1);
+}
+
+#[test]
+fn tiles_rest_on_the_edge_of_a_solid_world() {
+    let mut world = create_world();
+    fill(&mut world, &SAND, 100..=100, 150..=150);
+    run(&mut world, 1000);
+    assert!(world[point(100, WORLD_HEIGHT - 1)].is_some());
+}
+
+#[test]
+fn glue_sticks_where_it_lands() {
+    let mut world = create_world();
+    fill(&mut world, &WALL, 0..=199, 199..=199);
+    fill(&mut world, &GLUE, 100..=100, 180..=180);
+    run(&mut world, 400);
+    let landed = world[point(100, 198)].as_ref().unwrap();
+    assert_eq!(landed.element_id(), GLUE.id);
+    assert!(landed.velocity.is_zero());
+}
+
+/// Times the tick phases on a busy world: a pile of sand, a pool of water
+/// and some hot metal, all on the move. Run with
+/// `cargo test --release -- --ignored --nocapture benchmark_tick_phases`.
+#[test]
+#[ignore]
+fn benchmark_tick_phases() {
+    use std::time::Instant;
+
+    const RUNS: u32 = 100;
+    let mut world = create_world();
+    fill(&mut world, &SAND, 0..=79, 80..=199);
+    fill(&mut world, &WATER, 80..=159, 80..=199);
+    fill(&mut world, &METAL, 160..=199, 80..=199);
+    for (x, y) in iproduct!(0..WORLD_WIDTH, 80..WORLD_HEIGHT) {
+        let tile = world[point(x, y)].as_mut().unwrap();
+        tile.velocity = Vector {
+            x: (x % 3) as Speed,
+            y: 1,
+        };
+        if x >= 160 {
+            tile.temperature = Temperature::celsius(600);
+        }
+    }
+    let mut time = |name: &str, pass: &mut dyn FnMut(&mut World)| {
+        let start = Instant::now();
+        for _ in 0..RUNS {
+            pass(&mut world);
+        }
+        println!("{:<20} {:>10?}", name, start.elapsed() / RUNS);
+    };
+    let mut motion_queue = VecDeque::new();
+    time("thermal diffusion", &mut |world| {
+        world.apply_thermal_diffusion()
+    });
+    time("velocity", &mut |world| {
+        apply_velocity(world, &mut motion_queue);
+    });
+    time("periodic reactions", &mut |world| {
+        world.apply_periodic_reactions()
+    });
+}
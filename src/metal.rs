@@ -1,9 +1,12 @@
-use crate::element::{PeriodicReaction, FLUID, GRAVITY, PAUSE_EXEMPT, PERFECT_RESTITUTION};
+use crate::element::{
+    Channel, PeriodicReaction, PhaseChange, FLUID, GRAVITY, NO_FLAGS, PAUSE_EXEMPT,
+};
+use crate::temperature::Temperature;
 use crate::world::World;
 use crate::{tile::Tile, Color, Element, ElementId, ElementSetup, ELEMENT_DEFAULT, FIXED};
 use std::cmp;
 
-pub(crate) const NEUTRAL: u8 = 1;
+const NEUTRAL: u8 = 0;
 const NEUTRAL_COLOR: Color = [0.2, 0.2, 0.25, 1.0];
 
 pub const CHARGED_HEAD: u8 = 255;
@@ -13,11 +16,15 @@ const CHARGED_TAIL: u8 = 2;
 const CHARGED_TAIL_COLOR: Color = [0.3, 0.3, 0.7, 1.0];
 
 const LIQUID_COLOR: Color = [0.7, 0.3, 0.5, 1.0];
-const METAL_MELT_TEMPERATURE: i16 = 1500;
+const METAL_MELT_TEMPERATURE: Temperature = Temperature::celsius(1500);
 
 impl Tile {
     pub fn is_charged_metal(&self) -> bool {
-        self.element_id() == METAL.id && self.special_info() > 2
+        self.element_id() == METAL.id && self.channel(Channel::Charge) > CHARGED_TAIL
+    }
+
+    pub fn is_neutral_metal(&self) -> bool {
+        self.element_id() == METAL.id && self.channel(Channel::Charge) == NEUTRAL
     }
 }
 
@@ -25,7 +32,10 @@ pub static METAL: Element = Element {
     mass: 10,
     flags: FIXED,
     id: 7,
+    restitution: 1.0,
     color: NEUTRAL_COLOR,
+    channels: &[Channel::Charge],
+    state_channel: Some(Channel::Charge),
     state_colors: Some(|special_info| match special_info {
         CHARGED_TAIL => &CHARGED_TAIL_COLOR,
         NEUTRAL => &NEUTRAL_COLOR,
@@ -33,12 +43,9 @@ pub static METAL: Element = Element {
     }),
 
     periodic_reaction: PeriodicReaction::Some(|mut this, world| {
-        if this.temperature > METAL_MELT_TEMPERATURE {
-            this.set_element(LIQUID_METAL.id())
-        }
-        match this.special_info() {
+        match this.channel(Channel::Charge) {
             CHARGED_TAIL => {
-                this.edit_state(METAL.id(), NEUTRAL);
+                this.set_channel(Channel::Charge, NEUTRAL);
             }
             NEUTRAL => {
                 let mut adjacent_heads = 0;
@@ -47,48 +54,59 @@ pub static METAL: Element = Element {
                     if let Some(tile) = &world[i] {
                         if tile.is_charged_metal() {
                             adjacent_heads += 1;
-                            min_charge = cmp::min(min_charge, tile.special_info() - 1)
+                            min_charge = cmp::min(min_charge, tile.channel(Channel::Charge) - 1)
                         }
                     }
                 }
                 if adjacent_heads == 1 || adjacent_heads == 2 && min_charge > 2 {
-                    this.edit_state(METAL.id(), min_charge);
+                    this.set_channel(Channel::Charge, min_charge);
                 }
             }
             _ => {
-                this.edit_state(METAL.id(), CHARGED_TAIL);
+                this.set_channel(Channel::Charge, CHARGED_TAIL);
             }
         }
         Some(this)
     }),
+    heat_capacity: 5,
+    thermal_conductivity: 1.0,
+    phase_above: Some(PhaseChange {
+        temperature: METAL_MELT_TEMPERATURE,
+        element: &LIQUID_METAL,
+    }),
     ..ELEMENT_DEFAULT
 };
 
 pub static LIQUID_METAL: Element = Element {
     flags: FLUID | GRAVITY | PAUSE_EXEMPT,
     color: LIQUID_COLOR,
-    mass: 10,
+    mass: 70,
     id: 17,
-    periodic_reaction: PeriodicReaction::Some(|mut this, _world| {
-        if this.temperature < METAL_MELT_TEMPERATURE - 1 {
-            this.set_element(METAL.id())
-        }
-        Some(this)
+    default_temperature: Temperature::celsius(METAL_MELT_TEMPERATURE.degrees_celsius() + 20),
+    heat_capacity: 5,
+    thermal_conductivity: 0.8,
+    latent_heat: 1500,
+    phase_below: Some(PhaseChange {
+        temperature: METAL_MELT_TEMPERATURE,
+        element: &METAL,
     }),
-    default_temperature: METAL_MELT_TEMPERATURE + 20,
     ..ELEMENT_DEFAULT
 };
 
 pub static ELECTRON: Element = Element {
     mass: 2,
-    flags: PERFECT_RESTITUTION,
+    flags: NO_FLAGS,
     id: 8,
+    restitution: 1.0,
+    loose_restitution: 1.0,
+    heat_capacity: 1,
     color: [0.5, 0.5, 1.0, 1.0],
+    channels: &[Channel::Age],
     periodic_reaction: PeriodicReaction::DecayToNothing {
-        lifetime: 8,
+        lifetime: 7,
         rarity: 8,
     },
-    default_temperature: 300,
+    default_temperature: Temperature::celsius(300),
     ..ELEMENT_DEFAULT
 };
 
@@ -96,7 +114,7 @@ pub struct ElectronSetup;
 impl ElementSetup for ElectronSetup {
     fn register_reactions(&self, world: &mut World) {
         world.register_collision_side_effect(&METAL, &ELECTRON, |mut metal, _electron, _world| {
-            metal.edit_state(METAL.id(), CHARGED_HEAD);
+            metal.set_channel(Channel::Charge, CHARGED_HEAD);
             (Some(metal), None)
         });
     }
@@ -1,26 +1,37 @@
 use crate::{
     dirt::{dirt_moisture, DIRT},
-    element::{Element, PeriodicReaction, FIXED, GRAVITY},
+    element::{Channel, Element, PeriodicReaction, FIXED, GRAVITY},
+    fire::{FIRE, MAKES_ASH},
     simple_elements::ELEMENT_DEFAULT,
+    temperature::Temperature,
     tile::{ElementState, Tile},
 };
 
+/// A new plant tile holding `sap` to grow with, and as wet as `moisture`
+fn plant_tile(sap: u8, moisture: u8, temperature: Temperature) -> Tile {
+    let mut tile = Tile::stationary(ElementState::default(PLANT.id()), temperature);
+    tile.set_channel(Channel::Sap, sap);
+    tile.set_channel(Channel::Moisture, moisture);
+    tile.save_state();
+    tile
+}
+
 pub static SEED: Element = Element {
     flags: GRAVITY,
     color: [0.5, 0.6, 0.1, 1.0],
     mass: 10,
     id: 19,
+    angle_of_repose: 35.0,
     periodic_reaction: PeriodicReaction::Some(|mut this, mut world| {
         let mut should_grow = false;
         if this.velocity.is_zero() {
             let dirt_or_empty_above = world
                 .above()
-                .as_ref()
-                .map_or(true, |x| x.element_id() == DIRT.id);
+                .is_some_and(|above| above.as_ref().is_none_or(|x| x.element_id() == DIRT.id));
 
             if dirt_or_empty_above {
                 world.for_each_neighbor(|neighbor| {
-                    if neighbor.as_ref().map_or(0, |x| dirt_moisture(&x)) > 64 {
+                    if neighbor.as_ref().map_or(0, dirt_moisture) > 64 {
                         should_grow = true;
                     }
                 });
@@ -39,26 +50,34 @@ pub static PLANT: Element = Element {
     color: [0.1, 0.8, 0.1, 1.0],
     mass: 3,
     id: 20,
+    channels: &[Channel::Sap, Channel::Moisture],
     periodic_reaction: PeriodicReaction::Some(|mut this, mut world| {
-        if let Some(below) = world.below() {
-            if below.special_info() > 10
-                && this.special_info() < 192
+        // Wet plants have to get hotter before they catch fire,
+        // and stay wet while they burn
+        let moisture = this.channel(Channel::Moisture);
+        if this.temperature > Temperature::celsius(200 + moisture as i16) {
+            this.edit_state(FIRE.id(), MAKES_ASH);
+            this.temperature += 300;
+            return Some(this);
+        }
+
+        if let Some(Some(below)) = world.below() {
+            if below.channel(Channel::Sap) > 10
+                && this.channel(Channel::Sap) < 192
                 && (below.element_id() == PLANT.id || below.element_id() == ROOT.id)
             {
-                this.adjust_info(10);
-                below.adjust_info(-10);
+                this.adjust_channel(Channel::Sap, 10);
+                below.adjust_channel(Channel::Sap, -10);
             }
         }
 
-        if this.special_info() > 20 {
-            let above = world.above();
-            let dirt_or_empty_above = above.as_ref().map_or(true, |x| x.element_id() == DIRT.id);
-            if dirt_or_empty_above {
-                *above = Some(Tile::stationary(
-                    ElementState::new(PLANT.id(), 1),
-                    this.temperature,
-                ));
-                this.adjust_info(-10)
+        if this.channel(Channel::Sap) > 20 {
+            if let Some(above) = world.above() {
+                let dirt_or_empty_above = above.as_ref().is_none_or(|x| x.element_id() == DIRT.id);
+                if dirt_or_empty_above {
+                    *above = Some(plant_tile(0, moisture, this.temperature));
+                    this.adjust_channel(Channel::Sap, -10)
+                }
             }
         }
 
@@ -72,35 +91,33 @@ pub static ROOT: Element = Element {
     color: [0.9, 0.7, 0.1, 1.0],
     mass: 10,
     id: 21,
+    channels: &[Channel::Moisture],
     periodic_reaction: PeriodicReaction::Some(|this, mut world| {
         let mut should_grow = false;
         let mut total_moisture: u8 = 0;
         let dirt_or_empty_above = world
             .above()
-            .as_ref()
-            .map_or(true, |x| x.element_id() == DIRT.id);
+            .is_some_and(|above| above.as_ref().is_none_or(|x| x.element_id() == DIRT.id));
 
         if dirt_or_empty_above {
             world.for_each_neighbor(|neighbor| {
-                let moisture = neighbor.as_ref().map_or(0, |x| dirt_moisture(&x));
+                let moisture = neighbor.as_ref().map_or(0, dirt_moisture);
                 if moisture > 64 {
                     if let Some(neighbor) = neighbor {
                         total_moisture = total_moisture.saturating_add(10);
-                        neighbor.adjust_info(-10);
+                        neighbor.adjust_channel(Channel::Moisture, -10);
                         should_grow = true;
                     }
                 }
             });
 
             if should_grow && total_moisture > 10 {
-                *world.above() = Some(Tile::stationary(
-                    ElementState::new(PLANT.id(), total_moisture),
-                    this.temperature,
-                ))
+                *world.above().unwrap() =
+                    Some(plant_tile(total_moisture, total_moisture, this.temperature))
             }
         }
 
-        if let Some(tile) = world.below() {
+        if let Some(Some(tile)) = world.below() {
             if dirt_moisture(tile) > 150 {
                 tile.set_element(ROOT.id());
             }
@@ -110,3 +127,31 @@ pub static ROOT: Element = Element {
     }),
     ..ELEMENT_DEFAULT
 };
+
+#[test]
+fn wet_plants_burn_later_and_stay_wet_while_they_burn() {
+    use crate::point;
+
+    let mut world = crate::create_world();
+    crate::fill(&mut world, &PLANT, 100..=100, 100..=100);
+    crate::fill(&mut world, &PLANT, 110..=110, 100..=100);
+    let wet = world[point(100, 100)].as_mut().unwrap();
+    wet.set_channel(Channel::Moisture, 200);
+    wet.save_state();
+    wet.temperature = Temperature::celsius(350);
+    world[point(110, 100)].as_mut().unwrap().temperature = Temperature::celsius(350);
+    world.apply_periodic_reactions();
+    let id_at =
+        |world: &crate::world::World, x| world[point(x, 100)].as_ref().unwrap().element_id();
+    assert_eq!(id_at(&world, 110), FIRE.id);
+    assert_eq!(id_at(&world, 100), PLANT.id);
+
+    world[point(100, 100)].as_mut().unwrap().temperature = Temperature::celsius(500);
+    world.apply_periodic_reactions();
+    let burning = world[point(100, 100)].as_ref().unwrap();
+    assert_eq!(burning.element_id(), FIRE.id);
+    assert_eq!(burning.channel(Channel::Moisture), 200);
+    world.apply_periodic_reactions();
+    let burning = world[point(100, 100)].as_ref().unwrap();
+    assert!(burning.channel(Channel::Moisture) < 200);
+}
@@ -1,5 +1,6 @@
-use crate::element::{Element, PeriodicReaction, GRAVITY, NO_FLAGS};
+use crate::element::{Element, Mass, PeriodicReaction, GRAVITY, NO_FLAGS};
 use crate::fire::{FIRE, MAKES_ASH};
+use crate::temperature::Temperature;
 use crate::FIXED;
 
 pub static ELEMENT_DEFAULT: Element = Element {
@@ -9,7 +10,21 @@ pub static ELEMENT_DEFAULT: Element = Element {
     id: 255,
     periodic_reaction: PeriodicReaction::None,
     state_colors: None,
-    default_temperature: 20,
+    channels: &[],
+    state_channel: None,
+    default_temperature: Temperature::celsius(20),
+    heat_capacity: 10,
+    thermal_conductivity: 0.3,
+    latent_heat: 0,
+    phase_above: None,
+    phase_below: None,
+    angle_of_repose: 0.0,
+    state_angles_of_repose: None,
+    burst_pressure: f64::INFINITY,
+    updraft: 0.0,
+    restitution: 0.5,
+    loose_restitution: 0.8,
+    friction: 0.0,
 };
 
 pub static SAND: Element = Element {
@@ -17,8 +32,11 @@ pub static SAND: Element = Element {
     color: [1.0, 1.0, 0.5, 1.0],
     mass: 10,
     id: 2,
+    heat_capacity: 8,
+    thermal_conductivity: 0.2,
+    angle_of_repose: 35.0,
     periodic_reaction: PeriodicReaction::Some(|mut this, _world| {
-        if this.temperature > 100 {
+        if this.temperature > Temperature::celsius(100) {
             this.edit_state(FIRE.id(), MAKES_ASH);
             this.temperature += 400
         }
@@ -32,13 +50,27 @@ pub static ROCK: Element = Element {
     color: [0.5, 0.5, 0.5, 1.0],
     mass: 50,
     id: 1,
+    heat_capacity: 8,
+    angle_of_repose: 40.0,
     ..ELEMENT_DEFAULT
 };
 
 pub static WALL: Element = Element {
     flags: FIXED,
     color: [1.0, 1.0, 1.0, 1.0],
-    mass: 127,
+    mass: Mass::MAX,
     id: 0,
+    thermal_conductivity: 0.02,
+    restitution: 1.0,
+    ..ELEMENT_DEFAULT
+};
+
+pub static FAN: Element = Element {
+    flags: FIXED,
+    color: [0.3, 0.6, 0.7, 1.0],
+    mass: Mass::MAX,
+    id: 22,
+    updraft: 40.0,
+    restitution: 1.0,
     ..ELEMENT_DEFAULT
 };
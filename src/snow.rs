@@ -1,7 +1,8 @@
-use crate::element::{Element, ElementId, ElementSetup, PeriodicReaction, GRAVITY};
+use crate::element::{Element, ElementId, ElementSetup, PhaseChange, GRAVITY};
 use crate::fire::FIRE;
 use crate::simple_elements::ELEMENT_DEFAULT;
-use crate::water::WATER;
+use crate::temperature::Temperature;
+use crate::water::{FREEZING_POINT, WATER};
 use crate::world::World;
 
 pub static SNOW: Element = Element {
@@ -9,13 +10,14 @@ pub static SNOW: Element = Element {
     color: [0.9, 0.9, 1.0, 1.0],
     mass: 10,
     id: 11,
-    periodic_reaction: PeriodicReaction::Some(|mut this, _world| {
-        if this.temperature > 1 {
-            this.set_element(WATER.id());
-        }
-        Some(this)
+    default_temperature: Temperature::celsius(-20),
+    heat_capacity: 20,
+    thermal_conductivity: 0.2,
+    angle_of_repose: 60.0,
+    phase_above: Some(PhaseChange {
+        temperature: FREEZING_POINT,
+        element: &WATER,
     }),
-    default_temperature: -20,
     ..ELEMENT_DEFAULT
 };
 
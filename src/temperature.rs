@@ -0,0 +1,115 @@
+use std::ops::{Add, AddAssign, Sub, SubAssign};
+
+/// A temperature in whole degrees Celsius.
+///
+/// Never colder than `ABSOLUTE_ZERO` or hotter than `MAX`;
+/// arithmetic that would leave that range stops at its ends instead.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub struct Temperature(i16);
+
+impl Temperature {
+    /// The physical minimum, -273°C
+    pub const ABSOLUTE_ZERO: Temperature = Temperature(-273);
+    /// The hottest a tile can be, 32767°C
+    pub const MAX: Temperature = Temperature(i16::MAX);
+
+    pub const fn celsius(degrees: i16) -> Temperature {
+        if degrees < Self::ABSOLUTE_ZERO.0 {
+            Self::ABSOLUTE_ZERO
+        } else {
+            Temperature(degrees)
+        }
+    }
+
+    /// Like `celsius`, but for values which might not fit in the temperature range
+    pub fn saturating_celsius(degrees: i32) -> Temperature {
+        if degrees > Self::MAX.0 as i32 {
+            Self::MAX
+        } else if degrees < Self::ABSOLUTE_ZERO.0 as i32 {
+            Self::ABSOLUTE_ZERO
+        } else {
+            Temperature(degrees as i16)
+        }
+    }
+
+    pub fn from_kelvin(kelvin: i32) -> Temperature {
+        Self::saturating_celsius(kelvin + Self::ABSOLUTE_ZERO.0 as i32)
+    }
+
+    pub fn from_fahrenheit(fahrenheit: f64) -> Temperature {
+        Self::saturating_celsius(((fahrenheit - 32.0) * 5.0 / 9.0).round() as i32)
+    }
+
+    pub const fn degrees_celsius(self) -> i16 {
+        self.0
+    }
+
+    pub fn kelvin(self) -> i32 {
+        self.0 as i32 - Self::ABSOLUTE_ZERO.0 as i32
+    }
+
+    pub fn fahrenheit(self) -> f64 {
+        self.0 as f64 * 9.0 / 5.0 + 32.0
+    }
+
+    /// The temperature halfway between two others
+    pub fn midpoint(self, other: Temperature) -> Temperature {
+        Temperature(((self.0 as i32 + other.0 as i32) / 2) as i16)
+    }
+
+    pub fn saturating_add(self, degrees: i16) -> Temperature {
+        Self::saturating_celsius(self.0 as i32 + degrees as i32)
+    }
+
+    pub fn saturating_sub(self, degrees: i16) -> Temperature {
+        Self::saturating_celsius(self.0 as i32 - degrees as i32)
+    }
+}
+
+impl Add<i16> for Temperature {
+    type Output = Temperature;
+    fn add(self, degrees: i16) -> Temperature {
+        self.saturating_add(degrees)
+    }
+}
+
+impl AddAssign<i16> for Temperature {
+    fn add_assign(&mut self, degrees: i16) {
+        *self = self.saturating_add(degrees)
+    }
+}
+
+impl Sub<i16> for Temperature {
+    type Output = Temperature;
+    fn sub(self, degrees: i16) -> Temperature {
+        self.saturating_sub(degrees)
+    }
+}
+
+impl SubAssign<i16> for Temperature {
+    fn sub_assign(&mut self, degrees: i16) {
+        *self = self.saturating_sub(degrees)
+    }
+}
+
+/// The difference between two temperatures, in degrees
+impl Sub for Temperature {
+    type Output = i32;
+    fn sub(self, other: Temperature) -> i32 {
+        self.0 as i32 - other.0 as i32
+    }
+}
+
+#[test]
+fn temperature_saturates_at_both_ends() {
+    let mut temperature = Temperature::celsius(32000);
+    temperature += 1000;
+    temperature += 1000;
+    assert_eq!(temperature, Temperature::MAX);
+    assert_eq!(Temperature::celsius(-300), Temperature::ABSOLUTE_ZERO);
+    assert_eq!(Temperature::celsius(-200) - 100, Temperature::ABSOLUTE_ZERO);
+    assert_eq!(Temperature::ABSOLUTE_ZERO.kelvin(), 0);
+    assert_eq!(Temperature::from_kelvin(373), Temperature::celsius(100));
+    assert_eq!(Temperature::celsius(100).fahrenheit(), 212.0);
+    assert_eq!(Temperature::from_fahrenheit(32.0), Temperature::celsius(0));
+}
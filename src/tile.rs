@@ -4,23 +4,57 @@ use std::convert::TryFrom;
 use std::fmt::Display;
 
 mod element_state;
-use crate::element::{EFlag, Element, ElementId, SpecialElementInfo, PERFECT_RESTITUTION};
+mod heat;
+use crate::element::{Channel, EFlag, Element, ElementId, SpecialElementInfo};
+use crate::temperature::Temperature;
+use crate::world::BodyId;
 use crate::ELEMENTS;
 pub use element_state::*;
+pub use heat::{contact_conductance, dither};
 
-const BASE_RESTITUTION: f64 = 0.5;
-const BASE_COLLIDE_RESTITUTION: f64 = 0.8;
+/// Integer type of velocities, and of positions within a cell
+#[cfg(not(feature = "wide-physics"))]
+pub type Speed = i8;
+#[cfg(feature = "wide-physics")]
+pub type Speed = i16;
 
 #[derive(Clone, Copy)]
 pub struct Vector {
-    pub x: i8,
-    pub y: i8,
+    pub x: Speed,
+    pub y: Speed,
 }
 
 impl Vector {
     pub fn is_zero(&self) -> bool {
         self.x == 0 && self.y == 0
     }
+
+    /// Component of this vector along the unit vector `direction`
+    fn along(&self, (x, y): (f64, f64)) -> f64 {
+        self.x as f64 * x + self.y as f64 * y
+    }
+
+    /// The vector with the given components along the unit vector `normal`
+    /// and the one at right angles to it, rounded towards zero
+    fn from_components(normal: (f64, f64), along: f64, across: f64) -> Vector {
+        let (x, y) = (
+            along * normal.0 - across * normal.1,
+            along * normal.1 + across * normal.0,
+        );
+        Vector {
+            x: clamp_convert::<i32, Speed>(x.trunc() as i32),
+            y: clamp_convert::<i32, Speed>(y.trunc() as i32),
+        }
+    }
+}
+
+/// Changes to a tile's velocity and temperature that are held back until
+/// `save_state`, like the staged half of its `ElementData`, so that reactions
+/// later in the same pass still see the tile as it was
+#[derive(Clone, Copy, Default)]
+struct StagedChanges {
+    velocity: (i32, i32),
+    temperature: i32,
 }
 
 #[derive(Clone)]
@@ -28,9 +62,14 @@ pub struct Tile {
     //pub paused: bool,
     pub velocity: Vector,
     pub position: Vector,
-    // Celcius (for now)
-    pub temperature: i16,
+    pub temperature: Temperature,
+    /// Heat held by this tile that doesn't show in its temperature:
+    /// a fraction of a degree, or progress through a phase change
+    pub stored_heat: i32,
+    /// Rigid body this tile is part of, if any
+    pub body: Option<BodyId>,
     element_data: ElementData,
+    staged: StagedChanges,
 }
 
 impl Tile {
@@ -38,24 +77,30 @@ impl Tile {
         element_state: ElementState,
         position: Vector,
         velocity: Vector,
-        temperature: i16,
+        temperature: Temperature,
         //paused: bool,
     ) -> Tile {
         Tile {
             element_data: ElementData::new(element_state),
             //paused,
             temperature,
+            stored_heat: 0,
+            body: None,
             position,
             velocity,
+            staged: StagedChanges::default(),
         }
     }
-    pub fn stationary(element_state: ElementState, temperature: i16) -> Tile {
+    pub fn stationary(element_state: ElementState, temperature: Temperature) -> Tile {
         Tile {
             element_data: ElementData::new(element_state),
             temperature,
+            stored_heat: 0,
+            body: None,
             //paused: false,
             position: Vector { x: 0, y: 0 },
             velocity: Vector { x: 0, y: 0 },
+            staged: StagedChanges::default(),
         }
     }
 
@@ -95,12 +140,77 @@ impl Tile {
         self.adjust_info(-1);
     }
 
+    /// Value of `channel`, as of the last time the tile saved its state
+    pub fn channel(&self, channel: Channel) -> u8 {
+        self.element_data.channel(channel)
+    }
+
+    /// Sets `channel` once the tile saves its state
+    pub fn set_channel(&mut self, channel: Channel, value: u8) {
+        self.element_data.stage_channel(channel, value);
+    }
+
+    /// Changes `channel` by `delta` once the tile saves its state,
+    /// stopping at 0 and 255
+    pub fn adjust_channel(&mut self, channel: Channel, delta: i16) {
+        self.element_data.adjust_channel(channel, delta);
+    }
+
+    /// Value the element's `state_colors` and `state_angles_of_repose` go by
+    fn state_value(&self) -> u8 {
+        match self.get_element().state_channel {
+            Some(channel) => self.channel(channel),
+            None => self.special_info(),
+        }
+    }
+
     pub fn has_state(&self, element_id: ElementId, special_info: u8) -> bool {
         *self.get_state() == ElementState::new(element_id, special_info)
     }
 
+    /// Speeds the tile up by `(x, y)` once it saves its state
+    pub fn stage_velocity_change(&mut self, x: i32, y: i32) {
+        self.staged.velocity.0 += x;
+        self.staged.velocity.1 += y;
+    }
+
+    /// Warms the tile up by `degrees` once it saves its state
+    pub fn stage_temperature_change(&mut self, degrees: i32) {
+        self.staged.temperature += degrees;
+    }
+
+    /// Stages the changes made to velocity and temperature since the tile
+    /// was `before`, and puts them back how they were until it saves its state
+    pub fn stage_changes_since(&mut self, before: &Tile) {
+        self.stage_velocity_change(
+            self.velocity.x as i32 - before.velocity.x as i32,
+            self.velocity.y as i32 - before.velocity.y as i32,
+        );
+        self.stage_temperature_change(self.temperature - before.temperature);
+        self.velocity = before.velocity;
+        self.temperature = before.temperature;
+    }
+
+    /// Whether anything has been staged that saving the state would change
+    pub fn has_staged_changes(&self) -> bool {
+        let StagedChanges {
+            velocity,
+            temperature,
+        } = self.staged;
+        self.element_data.has_staged_changes() || velocity != (0, 0) || temperature != 0
+    }
+
     pub fn save_state(&mut self) {
         self.element_data.commit();
+        let StagedChanges {
+            velocity: (x, y),
+            temperature,
+        } = std::mem::take(&mut self.staged);
+        self.velocity.x = clamp_convert(self.velocity.x as i32 + x);
+        self.velocity.y = clamp_convert(self.velocity.y as i32 + y);
+        self.temperature = Temperature::saturating_celsius(
+            self.temperature.degrees_celsius() as i32 + temperature,
+        );
     }
 
     pub fn get_state(&self) -> &ElementState {
@@ -108,84 +218,88 @@ impl Tile {
     }
 
     pub fn color(&self) -> &[f32; 4] {
-        let state = self.get_state();
-        state
-            .element_id
-            .get_element()
-            .get_color(state.special_info.as_u8())
+        self.get_element().get_color(self.state_value())
+    }
+
+    pub fn angle_of_repose(&self) -> f64 {
+        self.get_element().get_angle_of_repose(self.state_value())
     }
 
     pub fn has_flag(&self, flag: EFlag) -> bool {
         self.get_element().has_flag(flag)
     }
 
-    pub fn elastic_collide_y(&mut self, particle2: &mut Tile) {
-        let (v1y, v2y) = elastic_collide(
-            self.velocity.y,
-            particle2.velocity.y,
-            self.get_element().mass,
-            particle2.get_element().mass,
+    /// Collides this tile with `particle2`, which lies one `step` away from it.
+    /// Velocities along the contact normal, the line between the two tiles,
+    /// bounce back according to the restitution of the two elements, and
+    /// friction drags their velocities across it towards each other.
+    /// Momentum is conserved, up to rounding.
+    pub fn elastic_collide(&mut self, particle2: &mut Tile, step: (i32, i32)) {
+        let normal = unit_vector(step);
+        let tangent = (-normal.1, normal.0);
+        let (v1, v2) = (
+            self.velocity.along(normal),
+            particle2.velocity.along(normal),
         );
-        if self.has_flag(PERFECT_RESTITUTION) {
-            self.velocity.y = v1y;
-        } else {
-            self.velocity.y = (v1y as f64 * BASE_COLLIDE_RESTITUTION).trunc() as i8;
-        }
-        if particle2.has_flag(PERFECT_RESTITUTION) {
-            particle2.velocity.y = v2y;
-        } else {
-            particle2.velocity.y = (v2y as f64 * BASE_COLLIDE_RESTITUTION).trunc() as i8;
+        if v1 <= v2 {
+            // Already moving apart
+            return;
         }
-    }
-
-    pub fn elastic_collide_x(&mut self, particle2: &mut Tile) {
-        let (v1x, v2x) = elastic_collide(
-            self.velocity.x,
-            particle2.velocity.x,
-            self.get_element().mass,
-            particle2.get_element().mass,
+        let (element1, element2) = (self.get_element(), particle2.get_element());
+        let (m1, m2) = (element1.mass as f64, element2.mass as f64);
+        let (new_v1, new_v2) =
+            elastic_collide(v1, v2, m1, m2, element1.loose_contact_restitution(element2));
+        let friction = element1.contact_friction(element2);
+        let (t1, t2) = (
+            self.velocity.along(tangent),
+            particle2.velocity.along(tangent),
         );
-        if self.has_flag(PERFECT_RESTITUTION) {
-            self.velocity.x = v1x;
+        let shared = if m1 + m2 > 0.0 {
+            (m1 * t1 + m2 * t2) / (m1 + m2)
         } else {
-            self.velocity.x = (v1x as f64 * BASE_COLLIDE_RESTITUTION).trunc() as i8;
-        }
-        if particle2.has_flag(PERFECT_RESTITUTION) {
-            particle2.velocity.x = v2x;
-        } else {
-            particle2.velocity.x = (v2x as f64 * BASE_COLLIDE_RESTITUTION).trunc() as i8;
-        }
+            (t1 + t2) / 2.0
+        };
+        self.velocity = Vector::from_components(normal, new_v1, t1 + friction * (shared - t1));
+        particle2.velocity = Vector::from_components(normal, new_v2, t2 + friction * (shared - t2));
     }
 
-    pub fn reflect_velocity_x(&mut self) {
-        if self.has_flag(PERFECT_RESTITUTION) {
-            self.velocity.x = -self.velocity.x;
-        } else {
-            self.velocity.x = (-(self.velocity.x as f64) * BASE_RESTITUTION).trunc() as i8;
+    /// Bounces this tile off a tile of `surface`, which is fixed in place
+    /// one `step` away from it
+    pub fn reflect_velocity(&mut self, step: (i32, i32), surface: &Element) {
+        let normal = unit_vector(step);
+        let tangent = (-normal.1, normal.0);
+        let speed = self.velocity.along(normal);
+        if speed <= 0.0 {
+            // Already moving away
+            return;
         }
+        let element = self.get_element();
+        self.velocity = Vector::from_components(
+            normal,
+            -speed * element.contact_restitution(surface),
+            self.velocity.along(tangent) * (1.0 - element.contact_friction(surface)),
+        );
     }
+}
 
-    pub fn reflect_velocity_y(&mut self) {
-        if self.has_flag(PERFECT_RESTITUTION) {
-            self.velocity.y = -self.velocity.y;
-        } else {
-            self.velocity.y = (-(self.velocity.y as f64) * BASE_RESTITUTION).trunc() as i8;
-        }
-    }
+fn unit_vector((x, y): (i32, i32)) -> (f64, f64) {
+    let length = (x as f64).hypot(y as f64);
+    (x as f64 / length, y as f64 / length)
 }
 
-fn elastic_collide(v1: i8, v2: i8, m1: i8, m2: i8) -> (i8, i8) {
-    let v1 = v1 as f64;
-    let v2 = v2 as f64;
-    let m1 = m1 as f64;
-    let m2 = m2 as f64;
-    let new_v1 =
-        (((m1 - m2) / (m1 + m2)) * v1 + 2.0 * m2 / (m1 + m2) * v2) * BASE_COLLIDE_RESTITUTION;
-    let new_v2 =
-        (((m2 - m1) / (m2 + m1)) * v2 + 2.0 * m1 / (m2 + m1) * v1) * BASE_COLLIDE_RESTITUTION;
+/// Velocities after a collision in one dimension. `restitution` is the fraction
+/// of the speed the two bodies approached each other with that they
+/// separate with: 1.0 for a perfectly elastic collision, 0.0 for a perfectly
+/// inelastic one.
+pub fn elastic_collide(v1: f64, v2: f64, m1: f64, m2: f64, restitution: f64) -> (f64, f64) {
+    let total_mass = m1 + m2;
+    if total_mass <= 0.0 {
+        return (v1, v2);
+    }
+    let momentum = m1 * v1 + m2 * v2;
     (
-        clamp_convert::<i32, i8>(new_v1.trunc() as i32),
-        clamp_convert::<i32, i8>(new_v2.trunc() as i32),
+        (momentum + m2 * restitution * (v2 - v1)) / total_mass,
+        (momentum + m1 * restitution * (v1 - v2)) / total_mass,
     )
 }
 
@@ -213,3 +327,212 @@ where
         )
     }
 }
+
+#[test]
+fn elastic_collide_conserves_momentum_across_mass_ratios() {
+    let masses = [
+        (1.0, 1.0),
+        (1.0, 10.0),
+        (3.0, 50.0),
+        (10.0, 127.0),
+        (127.0, 2.0),
+    ];
+    let velocities = [(-40.0, 30.0), (50.0, 0.0), (0.0, -50.0), (20.0, 20.0)];
+    for &(m1, m2) in &masses {
+        for &(v1, v2) in &velocities {
+            for &restitution in &[0.0, 0.5, 0.8, 1.0] {
+                let (new_v1, new_v2) = elastic_collide(v1, v2, m1, m2, restitution);
+                let momentum = m1 * v1 + m2 * v2;
+                assert!((m1 * new_v1 + m2 * new_v2 - momentum).abs() < 1e-9);
+                let energy = m1 * v1 * v1 + m2 * v2 * v2;
+                let new_energy = m1 * new_v1 * new_v1 + m2 * new_v2 * new_v2;
+                assert!(new_energy <= energy + 1e-9);
+                if restitution == 1.0 {
+                    assert!((new_energy - energy).abs() < 1e-9);
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn perfectly_elastic_collision_swaps_equal_masses() {
+    assert_eq!(elastic_collide(30.0, -10.0, 5.0, 5.0, 1.0), (-10.0, 30.0));
+    assert_eq!(elastic_collide(30.0, -10.0, 5.0, 5.0, 0.0), (10.0, 10.0));
+}
+
+#[test]
+fn collisions_of_the_heaviest_and_fastest_tiles_stay_in_range() {
+    use crate::element::Mass;
+    use crate::gas::GAS;
+    use crate::simple_elements::{FAN, WALL};
+
+    let heaviest = Mass::MAX as f64;
+    let (fastest, slowest) = (Speed::MAX as f64, Speed::MIN as f64);
+    for &(m1, m2) in &[(heaviest, heaviest), (heaviest, 1.0), (1.0, heaviest)] {
+        for &restitution in &[0.0, 0.5, 1.0] {
+            let (new_v1, new_v2) = elastic_collide(fastest, slowest, m1, m2, restitution);
+            let momentum = m1 * fastest + m2 * slowest;
+            assert!((m1 * new_v1 + m2 * new_v2 - momentum).abs() < 1e-6);
+        }
+    }
+    // Speeds a tile can't hold are clamped rather than wrapped around
+    assert_eq!(
+        clamp_convert::<i32, Speed>(Speed::MAX as i32 + 1),
+        Speed::MAX
+    );
+    assert_eq!(
+        clamp_convert::<i32, Speed>(Speed::MIN as i32 - 1),
+        Speed::MIN
+    );
+    assert_eq!(clamp_convert::<i32, Speed>(i32::MAX), Speed::MAX);
+
+    let tile = |element: &Element, x, y| {
+        let mut tile = Tile::stationary(
+            ElementState::default(element.id()),
+            Temperature::celsius(20),
+        );
+        tile.velocity = Vector { x, y };
+        tile
+    };
+    // A light tile struck by the heaviest one at full speed would bounce
+    // off faster than a tile can go
+    let mut heavy = tile(&WALL, Speed::MAX, 0);
+    let mut light = tile(&GAS, Speed::MIN, 0);
+    heavy.elastic_collide(&mut light, (1, 0));
+    assert_eq!(light.velocity.x, Speed::MAX);
+    assert!(heavy.velocity.x > 0);
+
+    // Head on along a diagonal, where the speed along the normal is
+    // more than a single component can hold
+    let mut first = tile(&WALL, Speed::MAX, Speed::MAX);
+    let mut second = tile(&FAN, Speed::MIN, Speed::MIN);
+    first.elastic_collide(&mut second, (1, 1));
+    // Being as heavy as each other, the two bounce back equally fast
+    assert!(first.velocity.x < 0 && first.velocity.y < 0);
+    assert!((first.velocity.x as i32 + second.velocity.x as i32).abs() <= 2);
+    assert!((first.velocity.y as i32 + second.velocity.y as i32).abs() <= 2);
+}
+
+#[test]
+fn tile_collisions_conserve_momentum_up_to_rounding() {
+    use crate::gas::GAS;
+    use crate::metal::{ELECTRON, LIQUID_METAL};
+    use crate::simple_elements::{ROCK, SAND};
+    use itertools::iproduct;
+
+    // A mix of masses, with and without perfect restitution
+    let elements = [&GAS, &ELECTRON, &SAND, &ROCK, &LIQUID_METAL];
+    let steps = [(1, 0), (0, 1), (1, 1), (-1, 1)];
+    let tile = |element: &Element, x, y| {
+        let mut tile = Tile::stationary(
+            ElementState::default(element.id()),
+            Temperature::celsius(20),
+        );
+        tile.velocity = Vector { x, y };
+        tile
+    };
+    for (element1, element2) in iproduct!(elements.iter(), elements.iter()) {
+        for &step in &steps {
+            let mut tile1 = tile(element1, 40, 30);
+            let mut tile2 = tile(element2, -20, 10);
+            let (m1, m2) = (element1.mass as i32, element2.mass as i32);
+            let momentum = |tile1: &Tile, tile2: &Tile| {
+                (
+                    m1 * tile1.velocity.x as i32 + m2 * tile2.velocity.x as i32,
+                    m1 * tile1.velocity.y as i32 + m2 * tile2.velocity.y as i32,
+                )
+            };
+            let before = momentum(&tile1, &tile2);
+            tile1.elastic_collide(&mut tile2, step);
+            let after = momentum(&tile1, &tile2);
+            // Each velocity component is rounded towards zero by up to 1
+            assert!((before.0 - after.0).abs() <= m1 + m2);
+            assert!((before.1 - after.1).abs() <= m1 + m2);
+        }
+    }
+}
+
+#[test]
+fn diagonal_collisions_exchange_both_components() {
+    use crate::gas::GAS;
+
+    let mut moving = Tile::stationary(ElementState::default(GAS.id()), Temperature::celsius(20));
+    let mut resting = moving.clone();
+    moving.velocity = Vector { x: 40, y: 40 };
+    moving.elastic_collide(&mut resting, (1, 1));
+    assert!(moving.velocity.x.abs() <= 1 && moving.velocity.y.abs() <= 1);
+    assert!(resting.velocity.x >= 39 && resting.velocity.y >= 39);
+}
+
+#[test]
+fn restitution_and_friction_come_from_both_elements() {
+    use crate::glue::GLUE;
+    use crate::simple_elements::{SAND, WALL};
+
+    let tile = |element: &Element| {
+        let mut tile = Tile::stationary(
+            ElementState::default(element.id()),
+            Temperature::celsius(20),
+        );
+        tile.velocity = Vector { x: 30, y: 20 };
+        tile
+    };
+    let mut sand = tile(&SAND);
+    sand.reflect_velocity((0, 1), &WALL);
+    assert_eq!((sand.velocity.x, sand.velocity.y), (30, -10));
+    let mut glue = tile(&GLUE);
+    glue.reflect_velocity((0, 1), &WALL);
+    assert!(glue.velocity.is_zero());
+    // Loose tiles bounce off each other more than off something fixed
+    let (mut first, mut second) = (tile(&SAND), tile(&SAND));
+    second.velocity = Vector { x: -30, y: 20 };
+    first.elastic_collide(&mut second, (1, 0));
+    assert_eq!((first.velocity.x, second.velocity.x), (-24, 24));
+}
+
+#[test]
+fn staged_changes_only_show_once_the_state_is_saved() {
+    use crate::simple_elements::SAND;
+
+    let mut tile = Tile::stationary(ElementState::default(SAND.id()), Temperature::celsius(20));
+    let before = tile.clone();
+    tile.velocity.x = 5;
+    tile.temperature += 100;
+    tile.stage_changes_since(&before);
+    tile.stage_velocity_change(0, -3);
+    tile.stage_temperature_change(-30);
+    assert!(tile.velocity.is_zero());
+    assert_eq!(tile.temperature, Temperature::celsius(20));
+    tile.save_state();
+    assert_eq!((tile.velocity.x, tile.velocity.y), (5, -3));
+    assert_eq!(tile.temperature, Temperature::celsius(90));
+}
+
+#[test]
+fn channels_are_staged_and_only_kept_by_elements_that_use_them() {
+    use crate::dirt::DIRT;
+    use crate::plant::ROOT;
+    use crate::simple_elements::SAND;
+
+    let mut tile = Tile::stationary(ElementState::default(DIRT.id()), Temperature::celsius(20));
+    tile.set_channel(Channel::Moisture, 100);
+    tile.adjust_channel(Channel::Moisture, 200);
+    assert_eq!(tile.channel(Channel::Moisture), 0);
+    tile.save_state();
+    assert_eq!(tile.channel(Channel::Moisture), 255);
+    // Moisture and special info are separate
+    tile.edit_state(DIRT.id(), 7);
+    tile.save_state();
+    assert_eq!(
+        (tile.special_info(), tile.channel(Channel::Moisture)),
+        (7, 255)
+    );
+    // Roots hold on to moisture, sand doesn't
+    tile.set_element(ROOT.id());
+    tile.save_state();
+    assert_eq!(tile.channel(Channel::Moisture), 255);
+    tile.set_element(SAND.id());
+    tile.save_state();
+    assert_eq!(tile.channel(Channel::Moisture), 0);
+}
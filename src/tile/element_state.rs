@@ -1,4 +1,4 @@
-use crate::element::{ElementId, SpecialElementInfo};
+use crate::element::{Channel, ElementId, SpecialElementInfo, CHANNEL_COUNT};
 
 #[derive(Clone, Copy, Eq, PartialEq, Debug)]
 pub struct ElementState {
@@ -26,6 +26,8 @@ impl ElementState {
 pub struct ElementData {
     current: ElementState,
     staged: ElementState,
+    current_channels: [u8; CHANNEL_COUNT],
+    staged_channels: [u8; CHANNEL_COUNT],
 }
 
 impl ElementData {
@@ -33,9 +35,25 @@ impl ElementData {
         ElementData {
             current: state,
             staged: state,
+            current_channels: [0; CHANNEL_COUNT],
+            staged_channels: [0; CHANNEL_COUNT],
         }
     }
 
+    pub fn channel(&self, channel: Channel) -> u8 {
+        self.current_channels[channel.index()]
+    }
+
+    pub fn stage_channel(&mut self, channel: Channel, value: u8) {
+        self.staged_channels[channel.index()] = value;
+    }
+
+    pub fn adjust_channel(&mut self, channel: Channel, delta: i16) {
+        let old = self.staged_channels[channel.index()] as i16;
+        let new = old.saturating_add(delta).clamp(0, 255) as u8;
+        self.stage_channel(channel, new);
+    }
+
     pub fn element_id(&self) -> ElementId {
         self.current.element_id
     }
@@ -62,10 +80,24 @@ impl ElementData {
     }
 
     pub fn stage(&mut self, element_state: ElementState) {
+        if element_state.element_id != self.staged.element_id {
+            // Only keep the channels the new element uses
+            let element = element_state.element_id.get_element();
+            for channel in Channel::ALL.iter() {
+                if !element.has_channel(*channel) {
+                    self.staged_channels[channel.index()] = 0;
+                }
+            }
+        }
         self.staged = element_state;
     }
 
+    pub fn has_staged_changes(&self) -> bool {
+        self.staged != self.current || self.staged_channels != self.current_channels
+    }
+
     pub fn commit(&mut self) {
         self.current = self.staged;
+        self.current_channels = self.staged_channels;
     }
 }
@@ -0,0 +1,161 @@
+use super::Tile;
+use crate::element::{Element, PhaseChange};
+use crate::temperature::Temperature;
+use rand::{thread_rng, Rng};
+
+/// Heat needed to turn a tile of `element` into the phase described by `change`,
+/// at the temperature of that change. Negative when the change releases heat.
+fn latent_heat_of(element: &Element, change: &PhaseChange) -> i32 {
+    let energy_at = |element: &Element| {
+        element.heat_capacity * change.temperature.degrees_celsius() as i32 + element.latent_heat
+    };
+    energy_at(change.element) - energy_at(element)
+}
+
+/// Conductivity of the contact between two materials, which is limited
+/// by the more insulating of the two
+pub fn contact_conductance(conductivity1: f64, conductivity2: f64) -> f64 {
+    if conductivity1 + conductivity2 <= 0.0 {
+        0.0
+    } else {
+        2.0 * conductivity1 * conductivity2 / (conductivity1 + conductivity2)
+    }
+}
+
+/// Rounds up or down, weighted by the fractional part of `value`,
+/// so that small flows of heat still even out over time.
+/// `dither` is a number in [0, 1) deciding which way a given value goes.
+fn round_dithered(value: f64, dither: f64) -> i32 {
+    (value + dither).floor() as i32
+}
+
+/// A number in [0, 1) that looks random, but only depends on `seed`
+pub fn dither(seed: u64) -> f64 {
+    // splitmix64
+    let mut z = seed.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^= z >> 31;
+    (z >> 11) as f64 / (1u64 << 53) as f64
+}
+
+impl Tile {
+    /// Total heat energy of this tile, including the latent heat of its phase
+    pub fn thermal_energy(&self) -> i64 {
+        let element = self.get_element();
+        element.heat_capacity as i64 * self.temperature.degrees_celsius() as i64
+            + self.stored_heat as i64
+            + element.latent_heat as i64
+    }
+
+    /// The heat that moves from this tile into `other` when `fraction` of
+    /// the heat that would bring both to the same temperature is exchanged.
+    /// Negative when `other` is the hotter tile.
+    pub fn heat_flow_to(&self, other: &Tile, fraction: f64, dither: f64) -> i32 {
+        let capacity1 = self.get_element().heat_capacity as f64;
+        let capacity2 = other.get_element().heat_capacity as f64;
+        let difference = (self.temperature - other.temperature) as f64;
+        round_dithered(
+            fraction * difference * capacity1 * capacity2 / (capacity1 + capacity2),
+            dither,
+        )
+    }
+
+    /// Like `heat_flow_to`, but into something too large for the exchange
+    /// to change its temperature, such as the surrounding air.
+    pub fn heat_flow_to_reservoir(
+        &self,
+        temperature: Temperature,
+        fraction: f64,
+        dither: f64,
+    ) -> i32 {
+        let capacity = self.get_element().heat_capacity as f64;
+        let difference = (self.temperature - temperature) as f64;
+        round_dithered(fraction * difference * capacity, dither)
+    }
+
+    /// Moves `fraction` of the heat that would bring both tiles to the same
+    /// temperature from the hotter tile into the colder one.
+    /// Call `settle_heat` on both tiles afterwards.
+    pub fn exchange_heat(&mut self, other: &mut Tile, fraction: f64) {
+        let flow = self.heat_flow_to(other, fraction, thread_rng().gen());
+        self.stored_heat -= flow;
+        other.stored_heat += flow;
+    }
+
+    /// Folds stored heat into the tile's temperature, changing phase
+    /// once enough latent heat has been absorbed or released.
+    pub fn settle_heat(&mut self) {
+        loop {
+            let element = self.get_element();
+            let capacity = element.heat_capacity;
+            let temperature =
+                self.temperature.degrees_celsius() as i32 + self.stored_heat.div_euclid(capacity);
+            let remainder = self.stored_heat.rem_euclid(capacity);
+            let (change, leftover) = if let Some(change) = element
+                .phase_above
+                .filter(|change| temperature >= change.temperature.degrees_celsius() as i32)
+            {
+                // Hold at the transition temperature until the latent heat is absorbed
+                let held = (temperature - change.temperature.degrees_celsius() as i32) * capacity
+                    + remainder;
+                let needed = latent_heat_of(element, &change);
+                self.temperature = change.temperature;
+                if held < needed {
+                    self.stored_heat = held;
+                    return;
+                }
+                (change, held - needed)
+            } else if let Some(change) = element
+                .phase_below
+                .filter(|change| temperature < change.temperature.degrees_celsius() as i32)
+            {
+                // Hold at the transition temperature until the latent heat is released
+                let held = (temperature - change.temperature.degrees_celsius() as i32) * capacity
+                    + remainder;
+                let released = -latent_heat_of(element, &change);
+                self.temperature = change.temperature;
+                if -held < released {
+                    self.stored_heat = held;
+                    return;
+                }
+                (change, held + released)
+            } else {
+                let clamped = Temperature::saturating_celsius(temperature);
+                // Heat beyond the range of the temperature type stays stored
+                self.stored_heat =
+                    remainder + (temperature - clamped.degrees_celsius() as i32) * capacity;
+                self.temperature = clamped;
+                return;
+            };
+            self.stored_heat = leftover;
+            self.set_element(change.element.id());
+            self.save_state();
+        }
+    }
+}
+
+#[test]
+fn water_holds_at_boiling_point_until_latent_heat_is_absorbed() {
+    use crate::tile::ElementState;
+    use crate::water::{BOILING_POINT, STEAM, WATER};
+
+    let mut tile = Tile::stationary(ElementState::default(WATER.id()), BOILING_POINT);
+    let latent_heat = latent_heat_of(&WATER, WATER.phase_above.as_ref().unwrap());
+    let energy = tile.thermal_energy();
+
+    tile.stored_heat += latent_heat - 1;
+    tile.settle_heat();
+    assert_eq!(tile.element_id(), WATER.id);
+    assert_eq!(tile.temperature, BOILING_POINT);
+    assert_eq!(tile.thermal_energy(), energy + latent_heat as i64 - 1);
+
+    tile.stored_heat += 1 + STEAM.heat_capacity * 5;
+    tile.settle_heat();
+    assert_eq!(tile.element_id(), STEAM.id);
+    assert_eq!(tile.temperature, BOILING_POINT + 5);
+    assert_eq!(
+        tile.thermal_energy(),
+        energy + (latent_heat + STEAM.heat_capacity * 5) as i64
+    );
+}
@@ -1,6 +1,6 @@
 use crate::{
-    point, ElementState, Tile, Vector, World, FIRE, GAS, ROCK, SAND, WALL, WORLD_HEIGHT,
-    WORLD_WIDTH,
+    point, ElementState, Temperature, Tile, Vector, World, FIRE, GAS, ROCK, SAND, WALL,
+    WORLD_HEIGHT, WORLD_WIDTH,
 };
 use rand::{self, Rng};
 
@@ -10,7 +10,7 @@ fn populate_world_bullet(world: &mut World) {
         ElementState::default(GAS.id()),
         Vector { x: 0, y: 0 },
         Vector { x: 127, y: 0 },
-        20,
+        Temperature::celsius(20),
         //false,
     ))
 }
@@ -24,7 +24,7 @@ pub fn populate_world_water_bubble(world: &mut World) {
                 Vector { x: 0, y: 0 },
                 Vector { x: 0, y: 0 },
                 //true,
-                20,
+                Temperature::celsius(20),
             ))
         }
     }
@@ -35,7 +35,7 @@ pub fn populate_world_water_bubble(world: &mut World) {
                 ElementState::default(SAND.id()),
                 Vector { x: 0, y: -1 },
                 Vector { x: 0, y: 0 },
-                20,
+                Temperature::celsius(20),
                 //false,
             ))
         }
@@ -51,7 +51,7 @@ pub fn populate_world_pileup(world: &mut World) {
                 ElementState::default(GAS.id()),
                 Vector { x: 0, y: 0 },
                 Vector { x: 0, y: 0 },
-                20,
+                Temperature::celsius(20),
                 //false,
             ))
         }
@@ -69,7 +69,7 @@ pub fn populate_world_pileup(world: &mut World) {
                     x: -10,
                     y: 0, //10,
                 },
-                20,
+                Temperature::celsius(20),
                 //false,
             ))
         }
@@ -78,14 +78,24 @@ pub fn populate_world_pileup(world: &mut World) {
 
 pub fn create_walls(world: &mut World) {
     for i in 0..WORLD_WIDTH {
-        world[point(i, 0)] = Some(Tile::stationary(ElementState::default(WALL.id()), 20));
-        world[point(i, WORLD_HEIGHT - 1)] =
-            Some(Tile::stationary(ElementState::default(WALL.id()), 20));
+        world[point(i, 0)] = Some(Tile::stationary(
+            ElementState::default(WALL.id()),
+            Temperature::celsius(20),
+        ));
+        world[point(i, WORLD_HEIGHT - 1)] = Some(Tile::stationary(
+            ElementState::default(WALL.id()),
+            Temperature::celsius(20),
+        ));
     }
     for i in 0..WORLD_HEIGHT {
-        world[point(0, i)] = Some(Tile::stationary(ElementState::default(WALL.id()), 20));
-        world[point(WORLD_WIDTH - 1, i)] =
-            Some(Tile::stationary(ElementState::default(WALL.id()), 20));
+        world[point(0, i)] = Some(Tile::stationary(
+            ElementState::default(WALL.id()),
+            Temperature::celsius(20),
+        ));
+        world[point(WORLD_WIDTH - 1, i)] = Some(Tile::stationary(
+            ElementState::default(WALL.id()),
+            Temperature::celsius(20),
+        ));
     }
 }
 
@@ -110,7 +120,7 @@ fn populate_world(world: &mut World) {
                 x: rng.gen_range(-1, 1),
                 y: rng.gen_range(-1, 1),
             },
-            20,
+            Temperature::celsius(20),
             //false,
         ))
     }
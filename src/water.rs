@@ -1,47 +1,56 @@
 use crate::element::{
-    Element, PeriodicReaction, FLUID, GRAVITY, PAUSE_EXEMPT, PERFECT_RESTITUTION,
+    Element, PeriodicReaction, PhaseChange, BUOYANT, FLUID, GASEOUS, GRAVITY, PAUSE_EXEMPT,
+    RESTLESS,
 };
 use crate::simple_elements::ELEMENT_DEFAULT;
 use crate::snow::SNOW;
+use crate::temperature::Temperature;
 use rand::Rng;
 
+pub const BOILING_POINT: Temperature = Temperature::celsius(100);
+pub const FREEZING_POINT: Temperature = Temperature::celsius(0);
+
 pub static WATER: Element = Element {
     flags: GRAVITY | PAUSE_EXEMPT | FLUID,
     color: [0.0, 0.0, 1.0, 1.0],
     mass: 8,
     id: 6,
-    periodic_reaction: PeriodicReaction::Some(|mut this, _world| {
-        if this.temperature > 100 {
-            // Over designated boiling point, become steam
-            this.set_element(STEAM.id())
-        } else if this.temperature < 0 {
-            this.set_element(SNOW.id())
-        } else {
-            // Water "jiggles" slightly
-            this.velocity.x += rand::thread_rng().gen_range(-3, 3 + 1);
-        }
-        Some(this)
+    heat_capacity: 40,
+    thermal_conductivity: 0.4,
+    latent_heat: 600,
+    phase_above: Some(PhaseChange {
+        temperature: BOILING_POINT,
+        element: &STEAM,
+    }),
+    phase_below: Some(PhaseChange {
+        temperature: FREEZING_POINT,
+        element: &SNOW,
     }),
     ..ELEMENT_DEFAULT
 };
 
 pub static STEAM: Element = Element {
-    flags: PAUSE_EXEMPT | PERFECT_RESTITUTION | FLUID,
+    flags: PAUSE_EXEMPT | FLUID | GASEOUS | BUOYANT | RESTLESS,
     color: [0.8, 0.8, 1.0, 1.0],
-    mass: 8,
+    mass: 2,
     id: 16,
-    periodic_reaction: PeriodicReaction::Some(|mut this, _world| {
-        if this.temperature < 99 {
-            this.set_element(WATER.id())
-        } else {
-            // Jiggle, to keep boiling water from staying still
-            if this.velocity.x.abs() < 2 && this.velocity.y.abs() < 2 {
-                this.velocity.x += rand::thread_rng().gen_range(-3, 3 + 1);
-                this.velocity.y += rand::thread_rng().gen_range(-3, 3 + 1);
-            }
+    periodic_reaction: PeriodicReaction::Some(|mut this, mut world| {
+        // Jiggle, to keep boiling water from staying still
+        if this.velocity.x.abs() < 2 && this.velocity.y.abs() < 2 {
+            this.velocity.x += world.rng().gen_range(-3, 3 + 1);
+            this.velocity.y += world.rng().gen_range(-3, 3 + 1);
         }
         Some(this)
     }),
-    default_temperature: 120,
+    default_temperature: Temperature::celsius(120),
+    heat_capacity: 20,
+    thermal_conductivity: 0.1,
+    latent_heat: 6600,
+    restitution: 1.0,
+    loose_restitution: 1.0,
+    phase_below: Some(PhaseChange {
+        temperature: BOILING_POINT,
+        element: &WATER,
+    }),
     ..ELEMENT_DEFAULT
 };
@@ -1,17 +1,41 @@
-use crate::element::{EFlag, Element, PeriodicReaction, FIXED, FLUID, GRAVITY, PAUSE_EXEMPT};
-use crate::tile::{ElementState, Tile};
-use crate::world_view::{CollisionView, NeighborhoodView};
+use crate::element::{EFlag, Element, BUOYANT, FIXED, FLUID, GASEOUS, GRAVITY, PAUSE_EXEMPT};
+use crate::tile::{contact_conductance, dither, ElementState, Speed, Tile};
+use crate::world_view::CollisionView;
 use crate::{
-    adjacent_x, neighbor_count, neighbors, PAUSE_VELOCITY, WORLD_HEIGHT, WORLD_SIZE, WORLD_WIDTH,
+    cell_within, coords, in_bounds, neighbors_within, offset, point, step_between, MIN_POSITION,
+    PAUSE_VELOCITY, POSITIONS_PER_CELL, WORLD_HEIGHT, WORLD_SIZE, WORLD_WIDTH,
 };
 use rand::Rng;
-use std::convert::TryInto;
 use std::{
-    collections::HashMap,
+    cmp::Ordering,
+    collections::{HashMap, HashSet},
     ops::{Index, IndexMut},
 };
 
+mod fields;
+mod pressure;
+mod reactions;
+mod rigid_body;
+mod scan;
+mod settings;
+mod sleep;
+mod wind;
+pub use fields::{FieldId, FieldRules, LIGHT};
+pub use rigid_body::{BodyId, RigidBody};
+pub use settings::{Boundary, ScanOrder, WorldSettings};
+pub use wind::Wind;
+
 const EMPTY_TILE: Option<Tile> = None;
+/// Fraction of the temperature difference between two perfectly conducting
+/// neighbors that is evened out by each pass of thermal diffusion
+const THERMAL_DIFFUSION_RATE: f64 = 1.0 / 9.0;
+/// How many tiles of a body of liquid are searched for somewhere lower to flow to
+const LIQUID_SEARCH_LIMIT: usize = 256;
+/// How many degrees hotter than its surroundings a gas has to be
+/// for each step of extra upward speed it gains in a gravity pass
+const BUOYANCY_TEMPERATURE_STEP: i32 = 50;
+/// Most upward speed a gas can gain in one gravity pass from being hot
+const MAX_HEAT_LIFT: i32 = 4;
 
 type Grid = [Option<Tile>; (WORLD_HEIGHT * WORLD_WIDTH) as usize];
 type CollisionSideEffect =
@@ -51,7 +75,7 @@ impl<T> TableRow<T> {
             }
         }
         // otherwise, return nothing.
-        return None;
+        None
     }
 }
 pub struct ElementAndFlagTable<T> {
@@ -84,6 +108,28 @@ pub struct World {
     collision_side_effects: HashMap<(u8, u8), CollisionSideEffect>,
     collision_reactions: HashMap<(u8, u8), CollisionReaction>,
     collision_reactions_by_flags: ElementAndFlagTable<CollisionReaction>,
+    settings: WorldSettings,
+    heat_deltas: Vec<i32>,
+    diffusion_pass: u64,
+    reaction_pass: u64,
+    /// Ticks since the world was created, which pick the scan order
+    tick: u64,
+    pressure: Vec<f64>,
+    wind: Vec<Wind>,
+    next_wind: Vec<Wind>,
+    bodies: HashMap<BodyId, RigidBody>,
+    next_body_id: BodyId,
+    /// Scalar fields layered over the grid, one value per cell
+    fields: Vec<Vec<f64>>,
+    /// Name and rules of each of `fields`
+    field_info: Vec<(&'static str, FieldRules)>,
+    /// Which chunks of the world are simulated this tick
+    awake: Vec<bool>,
+    /// Chunks woken since the last `update_sleep`, which stay awake for it
+    woken: Vec<bool>,
+    /// Chunks where the last pass of periodic reactions changed something,
+    /// which stay awake until the next pass
+    reacting: Vec<bool>,
 }
 
 pub struct Neighborhood<'a, T> {
@@ -116,7 +162,7 @@ impl<'a, T> Neighborhood<'a, T> {
     }
 }
 
-fn mutate_neighborhood<T>(slice: &mut [T], index: usize) -> (&mut T, Neighborhood<T>) {
+fn mutate_neighborhood<T>(slice: &mut [T], index: usize) -> (&mut T, Neighborhood<'_, T>) {
     let (before, center_and_after) = slice.split_at_mut(index);
     let (center, after) = center_and_after.split_at_mut(1);
     (&mut center[0], Neighborhood::new(before, after))
@@ -145,6 +191,65 @@ impl<U> PairwiseMutate for [U] {
     }
 }
 
+/// Whether a tile taking `step` pushes through the fluid in its way.
+/// Denser tiles sink through lighter fluids, and lighter tiles rise through
+/// denser ones, faster the bigger the difference in density.
+/// Moving sideways to `down`, or where there is no gravity, only mixes
+/// fluids of the same density.
+fn displaces(moving: &Tile, fluid: &Tile, (dx, dy): (i32, i32), down: Option<(i32, i32)>) -> bool {
+    let moving_density = moving.get_element().mass as f64;
+    let fluid_density = fluid.get_element().mass as f64;
+    let along = down.map_or(0, |(down_x, down_y)| dx * down_x + dy * down_y);
+    let (heavier, lighter) = match along.cmp(&0) {
+        Ordering::Greater => (moving_density, fluid_density),
+        Ordering::Less => (fluid_density, moving_density),
+        Ordering::Equal => {
+            return moving_density == fluid_density && rand::thread_rng().gen_bool(0.5);
+        }
+    };
+    heavier > lighter && rand::thread_rng().gen_bool((heavier - lighter) / heavier)
+}
+
+/// Step at right angles to `down`, the way tiles spread out along the
+/// ground, with `direction` of 1 or -1 picking the side.
+/// When gravity points straight down, 1 is to the right.
+fn sideways((dx, dy): (i32, i32), direction: i32) -> (i32, i32) {
+    (direction * dy, -direction * dx)
+}
+
+/// How long ago, as fractions of a tick, a tile whose sub-cell `position`
+/// has just moved `cells` cells along one axis crossed into each of them,
+/// from the last cell back to the first
+fn crossing_times(position: Speed, velocity: Speed, cells: i32) -> Vec<f64> {
+    let last = time_since_crossing(position, velocity, cells);
+    let count = cells.abs();
+    (0..count)
+        .map(|k| {
+            if last.is_nan() {
+                // Its velocity doesn't explain the move, so spread the crossings evenly
+                (k as f64 + 0.5) / count as f64
+            } else {
+                last + k as f64 * POSITIONS_PER_CELL as f64 / (velocity as f64).abs()
+            }
+        })
+        .collect()
+}
+
+/// How long ago, as a fraction of a tick, a tile whose sub-cell `position`
+/// has just wrapped around crossed into the next cell in direction `step`.
+/// NaN if its `velocity` doesn't take it that way.
+fn time_since_crossing(position: Speed, velocity: Speed, step: i32) -> f64 {
+    if velocity.signum() as i32 != step.signum() {
+        return f64::NAN;
+    }
+    let past_edge = if velocity > 0 {
+        position as f64 - MIN_POSITION as f64 + 0.5
+    } else {
+        (MIN_POSITION + POSITIONS_PER_CELL - 1) as f64 - position as f64 + 0.5
+    };
+    past_edge / (velocity as f64).abs()
+}
+
 impl IndexMut<usize> for World {
     fn index_mut(&mut self, i: usize) -> &mut Self::Output {
         &mut self.grid[i]
@@ -160,79 +265,270 @@ impl Index<usize> for World {
 
 impl World {
     pub fn new(elem_count: usize) -> World {
-        World {
+        let mut world = World {
             grid: Box::new([EMPTY_TILE; (WORLD_HEIGHT * WORLD_WIDTH) as usize]),
             collision_side_effects: HashMap::new(),
             collision_reactions: HashMap::new(),
             collision_reactions_by_flags: ElementAndFlagTable::new(elem_count),
-        }
+            settings: WorldSettings::default(),
+            heat_deltas: vec![0; WORLD_SIZE as usize],
+            diffusion_pass: 0,
+            reaction_pass: 0,
+            tick: 0,
+            pressure: vec![0.0; WORLD_SIZE as usize],
+            wind: vec![Wind::default(); WORLD_SIZE as usize],
+            next_wind: vec![Wind::default(); WORLD_SIZE as usize],
+            bodies: HashMap::new(),
+            next_body_id: 0,
+            fields: Vec::new(),
+            field_info: Vec::new(),
+            awake: vec![true; sleep::CHUNK_COUNT],
+            woken: vec![false; sleep::CHUNK_COUNT],
+            reacting: vec![false; sleep::CHUNK_COUNT],
+        };
+        world.add_field("light", fields::LIGHT_RULES);
+        world
+    }
+
+    pub fn settings(&self) -> &WorldSettings {
+        &self.settings
+    }
+
+    /// The settings, to change. Wakes the whole world, since any of them
+    /// might disturb it.
+    pub fn settings_mut(&mut self) -> &mut WorldSettings {
+        self.wake_all();
+        &mut self.settings
     }
 
     pub fn swap(&mut self, i: usize, j: usize) {
         self.grid.swap(i, j);
+        self.wake(i);
+        self.wake(j);
     }
 
     pub fn neighbor_count(&self, i: usize, predicate: impl Fn(&Tile) -> bool) -> usize {
-        neighbor_count(i, |j| match &self[j] {
-            None => false,
-            Some(tile) => predicate(tile),
-        })
+        self.neighbors(i)
+            .filter(|&j| match &self[j] {
+                None => false,
+                Some(tile) => predicate(tile),
+            })
+            .count()
+    }
+
+    /// The cells around `i`, following the world's boundary mode
+    pub fn neighbors(&self, i: usize) -> impl Iterator<Item = usize> + 'static {
+        neighbors_within(i, self.settings.boundary)
+    }
+
+    /// Index of the cell at `(x, y)`, following the world's boundary mode.
+    /// `None` if that is outside the world.
+    pub fn cell_at(&self, x: i32, y: i32) -> Option<usize> {
+        cell_within(x, y, self.settings.boundary)
     }
 
     pub fn state_at(&self, i: usize) -> Option<&ElementState> {
         self[i].as_ref().map(|x| x.get_state())
     }
 
-    pub fn move_particle(&mut self, source: usize, destination: usize) {
+    /// Moves the tile at `source` by `(dx, dy)`, which may be several cells,
+    /// a cell at a time in the order its velocity carried it across the edges
+    /// between them, so that it passes through every cell on the way,
+    /// including the one beside a corner it cuts.
+    /// A collision stops it going any further along that axis, but it keeps
+    /// the rest of its move along the other one.
+    /// It is lost if it leaves a world with a `Boundary::Open`.
+    pub fn move_along(&mut self, source: usize, (dx, dy): (i32, i32)) {
+        let (position, velocity) = match &self[source] {
+            Some(tile) => (tile.position, tile.velocity),
+            None => return,
+        };
+        let mut crossings: Vec<(f64, (i32, i32))> = crossing_times(position.x, velocity.x, dx)
+            .into_iter()
+            .map(|time| (time, (dx.signum(), 0)))
+            .chain(
+                crossing_times(position.y, velocity.y, dy)
+                    .into_iter()
+                    .map(|time| (time, (0, dy.signum()))),
+            )
+            .collect();
+        // Earliest first
+        crossings.sort_by(|a, b| b.0.total_cmp(&a.0));
+        let mut current = source;
+        let (mut blocked_x, mut blocked_y) = (false, false);
+        for k in 0..crossings.len() {
+            // Passing exactly through a corner, it runs into whatever is on
+            // either side instead of squeezing past
+            if let Some(&(time, step)) = crossings.get(k + 1) {
+                let (first_time, first_step) = crossings[k];
+                if time == first_time
+                    && self.empty_cell(current, first_step).is_some()
+                    && self.empty_cell(current, step).is_none()
+                {
+                    crossings.swap(k, k + 1);
+                }
+            }
+            let (_, step) = crossings[k];
+            let blocked = if step.0 != 0 {
+                &mut blocked_x
+            } else {
+                &mut blocked_y
+            };
+            if *blocked {
+                continue;
+            }
+            let next = match offset(current, step, self.settings.boundary) {
+                Some(next) => next,
+                None => {
+                    if self.settings.boundary == Boundary::Open {
+                        self[current] = None;
+                    }
+                    break;
+                }
+            };
+            if self.move_particle(current, next) {
+                current = next;
+            } else {
+                *blocked = true;
+            }
+        }
+    }
+
+    /// Moves the tile at `source` into the neighboring cell `destination`,
+    /// colliding with whatever is there. Returns whether it got there.
+    pub fn move_particle(&mut self, source: usize, destination: usize) -> bool {
+        let down = self.down_step(source);
         let (source_tile, dest_tile) = self.mutate_pair(source, destination);
         match (source_tile, dest_tile) {
             //match (world[source].as_mut(), world[destination].as_mut()) {
             (None, _) => {
                 //Source particle has moved for some other reason - nothing to do
+                false
             }
             (Some(_), None) => {
                 self.swap(source, destination);
+                true
             }
             (Some(ref mut s), Some(ref mut d)) => {
-                let new_temperature = (s.temperature + d.temperature) / 2;
-                s.temperature = new_temperature;
-                d.temperature = new_temperature;
+                s.exchange_heat(d, 1.0);
+                s.settle_heat();
+                d.settle_heat();
 
-                if adjacent_x(source, destination) {
-                    if d.has_flag(FIXED) {
-                        s.reflect_velocity_x();
-                    } else {
-                        s.elastic_collide_x(d);
-                    }
-                }
-                /*if adjacent_y(source, destination)*/
-                else if d.has_flag(FIXED) {
-                    s.reflect_velocity_y();
+                let step = step_between(source, destination);
+                if d.has_flag(FIXED) {
+                    s.reflect_velocity(step, d.get_element());
                 } else {
-                    s.elastic_collide_y(d);
+                    s.elastic_collide(d, step);
                 }
-                if d.has_flag(FLUID) && rand::thread_rng().gen_range(0, 2) == 0 {
+                let pushed_through = d.has_flag(FLUID) && displaces(s, d, step, down);
+                if pushed_through {
                     // Fluids don't collide, they just push through
                     self.swap(source, destination);
                 }
                 //self.trigger_collision_reactions(source, destination);
-                self.trigger_collision_effects(source, destination);
+                let reacted = self.trigger_collision_effects(source, destination);
+                pushed_through && !reacted
             }
         }
     }
 
+    /// Unit vector in the direction gravity pulls the tile at `position`,
+    /// or zero where there is no gravity
+    pub fn gravity_at(&self, position: usize) -> (f64, f64) {
+        let (x, y) = match self.settings.gravity_center {
+            Some((center_x, center_y)) => {
+                let (x, y) = coords(position);
+                ((center_x - x) as f64, (center_y - y) as f64)
+            }
+            None => self.settings.gravity_direction,
+        };
+        let length = x.hypot(y);
+        if length == 0.0 || self.settings.gravity_strength == 0.0 {
+            (0.0, 0.0)
+        } else {
+            (x / length, y / length)
+        }
+    }
+
+    /// Offset of the neighboring cell closest to the direction of gravity
+    /// at `position`, or `None` where there is no gravity
+    pub fn down_step(&self, position: usize) -> Option<(i32, i32)> {
+        let (x, y) = self.gravity_at(position);
+        let step = (x.round() as i32, y.round() as i32);
+        if step == (0, 0) {
+            None
+        } else {
+            Some(step)
+        }
+    }
+
+    /// The cell `(dx, dy)` away from `i`, if it is inside the world and empty
+    fn empty_cell(&self, i: usize, (dx, dy): (i32, i32)) -> Option<usize> {
+        offset(i, (dx, dy), self.settings.boundary).filter(|&j| self[j].is_none())
+    }
+
+    fn is_stable_support(&self, position: usize, (dx, dy): (i32, i32)) -> bool {
+        let j = match offset(position, (dx, dy), self.settings.boundary) {
+            Some(j) => j,
+            // Solid edges of the world count as stable, and tiles fall out of open ones
+            None => return self.settings.boundary == Boundary::Solid,
+        };
+        match &self[j] {
+            // Tiles of a rigid body can't hold each other up
+            Some(tile) if tile.body.is_some() => {
+                tile.body != self[position].as_ref().and_then(|tile| tile.body)
+                    && tile.velocity.is_zero()
+            }
+            Some(tile) => tile.has_flag(FIXED) || tile.velocity.is_zero(),
+            None => false,
+        }
+    }
+
+    /// Whether the tile at `position` is resting on something,
+    /// in whichever direction gravity currently pulls it
     pub fn has_stable_floor(&self, position: usize) -> bool {
-        match crate::below(position) {
-            Some(floor_position) => match &self[floor_position] {
-                Some(tile) => tile.has_flag(FIXED) || tile.velocity.is_zero(),
-                None => false,
-            },
-            None => true, // The bottom of the world counts as stable
+        match self.down_step(position) {
+            Some(step) => self.is_stable_support(position, step),
+            None => true, // Nothing to fall towards
+        }
+    }
+
+    /// Like `has_stable_floor`, for tiles rising against gravity
+    pub fn has_stable_ceiling(&self, position: usize) -> bool {
+        match self.down_step(position) {
+            Some((dx, dy)) => self.is_stable_support(position, (-dx, -dy)),
+            None => true,
         }
     }
 
+    /// Whether the tile at `i` clings on to something fixed in place beside it
+    /// this gravity pass, which is more likely the higher the friction between them
+    fn held_by_friction(&self, i: usize, rng: &mut impl Rng) -> bool {
+        let element = match &self[i] {
+            Some(tile) => tile.get_element(),
+            None => return false,
+        };
+        let friction = [(0, -1), (-1, 0), (1, 0), (0, 1)]
+            .iter()
+            .filter_map(|&step| offset(i, step, self.settings.boundary))
+            .filter_map(|j| self[j].as_ref())
+            .filter(|neighbor| neighbor.has_flag(FIXED))
+            .map(|neighbor| element.contact_friction(neighbor.get_element()))
+            .fold(0.0, f64::max);
+        friction > 0.0 && rng.gen_bool(friction.min(1.0))
+    }
+
+    /// Velocity change of `amount` in the direction of gravity at `position`,
+    /// with each component rounded up or down at random so that
+    /// fractional pulls still add up over time
+    fn pull(&self, position: usize, amount: f64, rng: &mut impl Rng) -> (Speed, Speed) {
+        let (x, y) = self.gravity_at(position);
+        let mut round = |value: f64| (value * amount + rng.gen::<f64>()).floor() as Speed;
+        (round(x), round(y))
+    }
+
     pub fn pause_particles(&mut self) {
-        for i in 0..WORLD_SIZE as usize {
+        for i in self.scan_cells() {
             match &self[i] {
                 None => {
                     continue;
@@ -259,117 +555,323 @@ impl World {
     }
 
     pub fn apply_gravity(&mut self) {
-        for i in 0..WORLD_SIZE as usize {
-            if self.has_stable_floor(i) {
+        let mut rng = rand::thread_rng();
+        let strength = self.settings.gravity_strength;
+        for i in self.scan_cells() {
+            match &self[i] {
+                Some(tile) if tile.has_flag(GRAVITY) && !tile.has_flag(FIXED) => {}
+                _ => continue,
+            }
+            if self.has_stable_floor(i) || self.held_by_friction(i, &mut rng) {
                 continue;
             }
-            match &mut self[i] {
-                Some(ref mut tile) => {
-                    if tile.has_flag(GRAVITY) && !tile.has_flag(FIXED) {
-                        tile.velocity.y = tile.velocity.y.saturating_add(1);
-                    }
-                }
-                None => {}
+            let (pull_x, pull_y) = self.pull(i, strength, &mut rng);
+            if let Some(ref mut tile) = &mut self[i] {
+                tile.velocity.x = tile.velocity.x.saturating_add(pull_x);
+                tile.velocity.y = tile.velocity.y.saturating_add(pull_y);
             }
         }
     }
 
-    pub fn apply_thermal_diffusion(&mut self) {
-        for i in 0..WORLD_SIZE as usize {
-            if self[i].is_some() {
-                let mut particle_count: usize = 1;
-                let mut total_temp: i64 = self[i].as_ref().map_or(0, |x| x.temperature as i64);
-                for j in neighbors(i) {
-                    if self[j].is_some() {
-                        particle_count += 1;
-                        total_temp += self[j].as_ref().map_or(0, |x| x.temperature as i64)
-                    }
-                }
-                let particle_count_large = particle_count as i64;
-                let average_temp = total_temp / particle_count_large;
-                let average_temp_remainder = total_temp % particle_count_large;
-                let remainder_ratio = (average_temp_remainder as f64) / (particle_count as f64);
-                let adjustment_ratio = remainder_ratio.abs();
-                let adjustment = if remainder_ratio.is_sign_negative() {
-                    -1i16
-                } else {
-                    1i16
-                };
-                let mut rng = rand::thread_rng();
-                let mut one_maybe = || {
-                    if rng.gen_bool(adjustment_ratio) {
-                        adjustment
-                    } else {
-                        0i16
-                    }
-                };
+    /// Pushes buoyant tiles and hot gases upwards, and lets them spread out
+    /// sideways under ceilings and around obstacles in their way.
+    pub fn apply_buoyancy(&mut self) {
+        let mut rng = rand::thread_rng();
+        let mut spreads = vec![];
+        // Cells that tiles are already spreading into this pass
+        let mut claimed = HashSet::new();
+        for i in self.scan_cells() {
+            let lift = self.lift(i);
+            if lift == 0 {
+                continue;
+            }
+            if !self.has_stable_ceiling(i) {
+                let (pull_x, pull_y) = self.pull(i, -(lift as f64), &mut rng);
                 if let Some(tile) = &mut self[i] {
-                    tile.temperature = average_temp.try_into().unwrap();
-                    tile.temperature += one_maybe();
+                    tile.velocity.x = tile.velocity.x.saturating_add(pull_x);
+                    tile.velocity.y = tile.velocity.y.saturating_add(pull_y);
                 }
-                for j in neighbors(i) {
-                    if let Some(tile) = &mut self[j] {
-                        tile.temperature = average_temp.try_into().unwrap();
-                        tile.temperature += one_maybe();
-                    }
+                continue;
+            }
+            let (dx, dy) = match self.down_step(i) {
+                Some(down) => down,
+                None => continue,
+            };
+            let direction = if rng.gen_bool(0.5) { 1 } else { -1 };
+            let (sx, sy) = sideways((dx, dy), direction);
+            let free = |step| self.empty_cell(i, step).filter(|j| !claimed.contains(j));
+            if let Some(beside) = free((sx, sy)) {
+                // Rise diagonally past the edge of the ceiling if possible
+                let destination = free((sx - dx, sy - dy)).unwrap_or(beside);
+                claimed.insert(destination);
+                spreads.push((i, destination));
+            }
+        }
+        // Tiles spread out after the whole grid is checked, so none moves twice
+        for (source, destination) in spreads {
+            self.swap(source, destination);
+        }
+    }
+
+    /// Upward speed the tile at `i` gains in a gravity pass. Buoyant elements
+    /// always rise, and any gas rises faster the hotter it is than its
+    /// surroundings, where empty cells are air at the ambient temperature.
+    fn lift(&self, i: usize) -> Speed {
+        let tile = match &self[i] {
+            Some(tile) if tile.has_flag(BUOYANT | GASEOUS) && !tile.has_flag(FIXED) => tile,
+            _ => return 0,
+        };
+        let base_lift = if tile.has_flag(BUOYANT) { 1 } else { 0 };
+        if !tile.has_flag(GASEOUS) {
+            return base_lift;
+        }
+        let ambient_temperature = self.settings.ambient_temperature;
+        let (total, count) = self
+            .neighbors(i)
+            .map(|j| match &self[j] {
+                Some(neighbor) => neighbor.temperature,
+                None => ambient_temperature,
+            })
+            .fold((0, 0), |(total, count), temperature| {
+                (total + temperature.degrees_celsius() as i32, count + 1)
+            });
+        let surroundings = if count == 0 {
+            ambient_temperature.degrees_celsius() as i32
+        } else {
+            total / count
+        };
+        let excess = tile.temperature.degrees_celsius() as i32 - surroundings;
+        base_lift + (excess / BUOYANCY_TEMPERATURE_STEP).clamp(0, MAX_HEAT_LIFT) as Speed
+    }
+
+    /// Lets granular tiles resting on a slope steeper than
+    /// their angle of repose slide diagonally down it.
+    pub fn apply_granular_sliding(&mut self) {
+        let mut rng = rand::thread_rng();
+        let mut slides = vec![];
+        for i in self.scan_cells() {
+            let tile = match &self[i] {
+                Some(tile) => tile,
+                None => continue,
+            };
+            let angle = tile.angle_of_repose();
+            if angle <= 0.0
+                || !tile.has_flag(GRAVITY)
+                || tile.has_flag(FIXED | FLUID)
+                || tile.body.is_some()
+            {
+                continue;
+            }
+            let (dx, dy) = match self.down_step(i) {
+                Some(down) => down,
+                None => continue,
+            };
+            if self.empty_cell(i, (dx, dy)).is_some() || self.held_by_friction(i, &mut rng) {
+                // Still falling, or stuck where it is
+                continue;
+            }
+            let first_direction = if rng.gen_bool(0.5) { 1 } else { -1 };
+            for &direction in &[first_direction, -first_direction] {
+                if let Some(destination) = self.slide_destination(i, (dx, dy), direction, angle) {
+                    slides.push((i, destination));
+                    break;
                 }
             }
         }
+        // Tiles slide after the whole grid is checked, so none slides twice
+        for (source, destination) in slides {
+            if self[destination].is_none() {
+                self.swap(source, destination);
+            }
+        }
     }
 
-    pub fn apply_periodic_reactions(&mut self) {
-        for i in 0..WORLD_SIZE as usize {
-            if let Some(tile) = self[i].clone() {
-                match tile.get_element().periodic_reaction {
-                    PeriodicReaction::Some(reaction) => {
-                        self[i] = reaction(tile, NeighborhoodView::new(self.grid.as_mut(), i));
-                    }
-                    PeriodicReaction::None => {
-                        // Do nothing
-                    }
-                    PeriodicReaction::DecayInto {
-                        element_id,
-                        lifetime,
-                        rarity,
-                    } => {
-                        if rand::thread_rng().gen_range(0, rarity) == 0 {
-                            let mut new_tile = tile.clone();
-                            new_tile.edit_state(
-                                tile.get_element().id(),
-                                tile.special_info().saturating_add(1),
-                            );
-                            // Increase "temperature" by one
-                            if new_tile.special_info() == lifetime {
-                                // If we hit 255, melt
-                                new_tile.set_element(element_id)
-                            }
-                            self[i] = Some(new_tile);
-                        }
+    /// Where the grain at `i` slides to in `direction`, if the ground beside
+    /// it drops away more steeply than its angle of repose.
+    /// A slope only drops a whole number of cells from one column to the
+    /// next, so the steps carry the fraction left over along the slope:
+    /// between columns `c` and `c + 1` it may drop
+    /// `floor((c + 1) * tan(angle)) - floor(c * tan(angle))` cells.
+    /// It may always drop one cell, as a grain moving over a smaller step
+    /// would only leave the same step behind it, so shallower angles than
+    /// 45 degrees rest at 45.
+    fn slide_destination(
+        &self,
+        i: usize,
+        (dx, dy): (i32, i32),
+        direction: i32,
+        angle: f64,
+    ) -> Option<usize> {
+        let tan = angle.to_radians().tan().min(WORLD_HEIGHT as f64);
+        let (x, y) = coords(i);
+        // Columns are counted across the direction of gravity
+        let column = (x * dy - y * dx).min(x * dy - y * dx + direction) as f64;
+        let drop = ((column + 1.0) * tan).floor() as i32 - (column * tan).floor() as i32;
+        let (sx, sy) = sideways((dx, dy), direction);
+        if (0..=drop.max(1)).all(|k| self.empty_cell(i, (sx + k * dx, sy + k * dy)).is_some()) {
+            self.empty_cell(i, (sx + dx, sy + dy))
+        } else {
+            None
+        }
+    }
+
+    /// Lets liquids that can't fall any further spread out sideways,
+    /// so that they level out and fill their containers.
+    pub fn apply_liquid_flow(&mut self) {
+        let mut rng = rand::thread_rng();
+        let mut flows = vec![];
+        // Cells that liquid is already flowing into this pass
+        let mut claimed = HashSet::new();
+        for i in self.scan_cells() {
+            let element_id = match &self[i] {
+                Some(tile) if tile.has_flag(FLUID) && tile.has_flag(GRAVITY) => tile.element_id(),
+                _ => continue,
+            };
+            let down = match self.down_step(i) {
+                Some(down) => down,
+                None => continue,
+            };
+            if self.empty_cell(i, down).is_some() || self.held_by_friction(i, &mut rng) {
+                // Still falling, or stuck where it is
+                continue;
+            }
+            let direction = if rng.gen_bool(0.5) { 1 } else { -1 };
+            let side = sideways(down, direction);
+            let destination = self.liquid_flow_destination(i, down, side, element_id, &claimed);
+            if let Some(destination) = destination {
+                claimed.insert(destination);
+                flows.push((i, destination));
+            }
+        }
+        // Liquids flow after the whole grid is checked, so none flows twice
+        for (source, destination) in flows {
+            self.swap(source, destination);
+        }
+    }
+
+    /// Where the liquid at `i` flows to, first trying the `side` step
+    /// at right angles to `down`, and leaving out `claimed` cells
+    fn liquid_flow_destination(
+        &self,
+        i: usize,
+        (dx, dy): (i32, i32),
+        (sx, sy): (i32, i32),
+        element_id: u8,
+        claimed: &HashSet<usize>,
+    ) -> Option<usize> {
+        let free = |step| self.empty_cell(i, step).filter(|j| !claimed.contains(j));
+        // Slide down diagonally, as long as that doesn't squeeze between two tiles
+        if let Some(beside) = free((sx, sy)) {
+            return free((sx + dx, sy + dy)).or(Some(beside));
+        }
+        // Liquid at the surface moves through the body of liquid underneath it
+        // to any lower gap, which levels it out and fills connected vessels
+        if offset(i, (-dx, -dy), self.settings.boundary).is_some_and(|j| self[j].is_some()) {
+            return None;
+        }
+        // Search depth first, trying downwards before sideways before upwards.
+        // This stays inside the edges of the world even when they wrap around,
+        // so that "lower" keeps its meaning.
+        let depth = |(x, y): (i32, i32)| x * dx + y * dy;
+        let start = coords(i);
+        let mut visited = HashSet::new();
+        let mut stack = vec![start];
+        visited.insert(start);
+        while let Some((x, y)) = stack.pop() {
+            for &(step_x, step_y) in &[(-dx, -dy), (-sx, -sy), (sx, sy), (dx, dy)] {
+                let (x, y) = (x + step_x, y + step_y);
+                if !in_bounds(x, y) || visited.len() >= LIQUID_SEARCH_LIMIT {
+                    continue;
+                }
+                match &self[point(x, y)] {
+                    None if depth((x, y)) > depth(start) && !claimed.contains(&point(x, y)) => {
+                        return Some(point(x, y))
                     }
-                    PeriodicReaction::DecayToNothing { lifetime, rarity } => {
-                        if rand::thread_rng().gen_range(0, rarity) == 0 {
-                            let mut new_tile = tile.clone();
-                            new_tile.edit_state(
-                                tile.get_element().id(),
-                                tile.special_info().saturating_add(1),
-                            );
-                            // Increase "temperature" by one
-                            if new_tile.special_info() == lifetime {
-                                // If we hit 255, melt
-                                self[i] = None
-                            } else {
-                                self[i] = Some(new_tile);
-                            }
-                        }
+                    Some(tile) if tile.element_id() == element_id && visited.insert((x, y)) => {
+                        stack.push((x, y));
                     }
+                    _ => {}
                 }
             }
         }
-        for i in 0..WORLD_SIZE as usize {
+        None
+    }
+
+    pub fn apply_thermal_diffusion(&mut self) {
+        self.diffusion_pass = self.diffusion_pass.wrapping_add(1);
+        // Work out every tile's change in heat before applying any of them,
+        // so the result doesn't depend on the order tiles are visited in.
+        let mut heat_deltas = std::mem::take(&mut self.heat_deltas);
+        for i in self.awake_cells() {
+            heat_deltas[i] = self.heat_gained(i);
+        }
+        for i in self.awake_cells() {
             if let Some(tile) = &mut self[i] {
-                tile.save_state();
+                tile.stored_heat += heat_deltas[i];
+                tile.settle_heat();
+            }
+        }
+        self.heat_deltas = heat_deltas;
+    }
+
+    /// Heat tile `i` gains from its neighbors in this diffusion pass.
+    /// Only reads the grid, so it can be computed for every tile independently.
+    fn heat_gained(&self, i: usize) -> i32 {
+        let tile = match &self[i] {
+            Some(tile) => tile,
+            None => return 0,
+        };
+        let ambient_temperature = self.settings.ambient_temperature;
+        let ambient_fraction = THERMAL_DIFFUSION_RATE
+            * contact_conductance(
+                tile.get_element().thermal_conductivity,
+                self.settings.cooling_rate,
+            );
+        let pair_dither = |first: usize, second: usize| {
+            dither(
+                (self.diffusion_pass * WORLD_SIZE as u64 + first as u64)
+                    .wrapping_mul(WORLD_SIZE as u64)
+                    .wrapping_add(second as u64),
+            )
+        };
+        let mut gained = 0;
+        // Cells past the edge of the world are always open to the environment
+        let border_contacts = 8 - self.neighbors(i).count() as i32;
+        if border_contacts > 0 && ambient_fraction > 0.0 {
+            gained -= border_contacts
+                * tile.heat_flow_to_reservoir(
+                    ambient_temperature,
+                    ambient_fraction,
+                    pair_dither(i, i),
+                );
+        }
+        for j in self.neighbors(i) {
+            // Both tiles of a pair must agree on the flow between them,
+            // so it is always worked out from the lower index to the higher
+            let (first, second) = (i.min(j), i.max(j));
+            let dither = pair_dither(first, second);
+            match &self[j] {
+                Some(neighbor) => {
+                    let fraction = THERMAL_DIFFUSION_RATE
+                        * contact_conductance(
+                            tile.get_element().thermal_conductivity,
+                            neighbor.get_element().thermal_conductivity,
+                        );
+                    if i == first {
+                        gained -= tile.heat_flow_to(neighbor, fraction, dither);
+                    } else {
+                        gained += neighbor.heat_flow_to(tile, fraction, dither);
+                    }
+                }
+                None if ambient_fraction > 0.0 => {
+                    // Empty cells are air at the ambient temperature
+                    gained -=
+                        tile.heat_flow_to_reservoir(ambient_temperature, ambient_fraction, dither);
+                }
+                None => {}
             }
         }
+        gained
     }
 
     pub fn register_flag_collision_reaction(
@@ -454,7 +956,14 @@ impl World {
             let (mut first_after, mut second_after) = reaction(
                 first_tile,
                 second_tile,
-                CollisionView::new(self.grid.as_mut(), first_index, second_index),
+                CollisionView::new(
+                    self.grid.as_mut(),
+                    &mut self.fields,
+                    &mut self.wind,
+                    first_index,
+                    second_index,
+                    self.settings.boundary,
+                ),
             );
             // Because the public methods on tiles edit the staged state,
             // We have to save states here.
@@ -526,7 +1035,10 @@ impl World {
 
     // returns (center, neighbors)
     // panics if self[index] is None
-    pub fn mutate_neighbors(&mut self, index: usize) -> (&mut Tile, Neighborhood<Option<Tile>>) {
+    pub fn mutate_neighbors(
+        &mut self,
+        index: usize,
+    ) -> (&mut Tile, Neighborhood<'_, Option<Tile>>) {
         let (center, nhood) = mutate_neighborhood(&mut *self.grid, index);
         match center.as_mut() {
             Some(mut_ref_tile) => (mut_ref_tile, nhood),
@@ -566,3 +1078,251 @@ pub fn mutate_neighborhood_test() {
         ]
     );
 }
+
+#[test]
+pub fn thermal_diffusion_conserves_energy() {
+    use crate::lava::LAVA;
+    use crate::metal::{LIQUID_METAL, METAL};
+    use crate::snow::SNOW;
+    use crate::{point, SAND, STEAM, WATER};
+
+    let elements = [&LAVA, &WATER, &SNOW, &METAL, &STEAM, &SAND, &LIQUID_METAL];
+    let mut world = World::new(crate::ELEMENTS.len());
+    for x in 10..30 {
+        for y in 10..30 {
+            let element = elements[(x * 7 + y * 3) as usize % elements.len()];
+            world[point(x, y)] = Some(Tile::stationary(
+                ElementState::default(element.id()),
+                element.default_temperature,
+            ));
+        }
+    }
+    let total_energy = |world: &World| -> i64 {
+        (0..WORLD_SIZE as usize)
+            .filter_map(|i| world[i].as_ref())
+            .map(Tile::thermal_energy)
+            .sum()
+    };
+    let element_ids = |world: &World| -> Vec<u8> {
+        (0..WORLD_SIZE as usize)
+            .filter_map(|i| world[i].as_ref())
+            .map(Tile::element_id)
+            .collect()
+    };
+
+    // Heat lost to the environment isn't counted
+    world.settings_mut().cooling_rate = 0.0;
+    let energy_before = total_energy(&world);
+    let elements_before = element_ids(&world);
+    for _ in 0..200 {
+        world.apply_thermal_diffusion();
+        assert_eq!(total_energy(&world), energy_before);
+    }
+    // Make sure phase changes actually happened along the way
+    assert_ne!(element_ids(&world), elements_before);
+}
+
+#[test]
+pub fn metal_conducts_heat_faster_than_glass() {
+    use crate::glass::GLASS;
+    use crate::metal::METAL;
+    use crate::point;
+    use crate::temperature::Temperature;
+
+    let mut world = World::new(crate::ELEMENTS.len());
+    world.settings_mut().cooling_rate = 0.0;
+    for (y, element) in [(10, &METAL), (20, &GLASS)].iter() {
+        for x in 10..20 {
+            let temperature = Temperature::celsius(if x == 10 { 1000 } else { 20 });
+            world[point(x, *y)] = Some(Tile::stationary(
+                ElementState::default(element.id()),
+                temperature,
+            ));
+        }
+    }
+    for _ in 0..100 {
+        world.apply_thermal_diffusion();
+    }
+    let temperature_at = |x, y| world[point(x, y)].as_ref().unwrap().temperature;
+    assert!(temperature_at(15, 10) > temperature_at(15, 20) + 10);
+}
+
+#[test]
+pub fn thermal_diffusion_is_symmetric() {
+    use crate::metal::METAL;
+    use crate::point;
+    use crate::temperature::Temperature;
+
+    let mut world = World::new(crate::ELEMENTS.len());
+    for (x, temperature) in [(9, 20), (10, 1000), (11, 20)].iter() {
+        world[point(*x, 10)] = Some(Tile::stationary(
+            ElementState::default(METAL.id()),
+            Temperature::celsius(*temperature),
+        ));
+    }
+    world.apply_thermal_diffusion();
+    // Both cold tiles should gain the same heat, give or take rounding
+    let energy_at = |x| world[point(x, 10)].as_ref().unwrap().thermal_energy();
+    assert!((energy_at(9) - energy_at(11)).abs() <= 1);
+}
+
+#[test]
+pub fn cold_ambient_temperature_freezes_water() {
+    use crate::snow::SNOW;
+    use crate::temperature::Temperature;
+    use crate::water::FREEZING_POINT;
+    use crate::{point, WATER};
+
+    let mut world = World::new(crate::ELEMENTS.len());
+    world.settings_mut().ambient_temperature = Temperature::celsius(-20);
+    world.settings_mut().cooling_rate = 1.0;
+    world[point(10, 10)] = Some(Tile::stationary(
+        ElementState::default(WATER.id()),
+        WATER.default_temperature,
+    ));
+    for _ in 0..1000 {
+        world.apply_thermal_diffusion();
+    }
+    let tile = world[point(10, 10)].as_ref().unwrap();
+    assert_eq!(tile.element_id(), SNOW.id);
+    assert!(tile.temperature < FREEZING_POINT);
+}
+
+#[test]
+fn only_hot_gas_rises() {
+    use crate::gas::GAS;
+    use crate::temperature::Temperature;
+
+    let mut world = World::new(0);
+    world[point(10, 10)] = Some(Tile::stationary(
+        ElementState::default(GAS.id()),
+        Temperature::celsius(20),
+    ));
+    world[point(20, 10)] = Some(Tile::stationary(
+        ElementState::default(GAS.id()),
+        Temperature::celsius(300),
+    ));
+    world.apply_buoyancy();
+    assert_eq!(world[point(10, 10)].as_ref().unwrap().velocity.y, 0);
+    assert!(world[point(20, 10)].as_ref().unwrap().velocity.y < 0);
+}
+
+#[test]
+fn tiles_fall_towards_the_gravity_center() {
+    use crate::simple_elements::SAND;
+    use crate::temperature::Temperature;
+
+    let mut world = World::new(0);
+    world.settings_mut().gravity_center = Some((100, 100));
+    for &(x, y) in &[(100, 50), (150, 100), (100, 150), (50, 100)] {
+        world[point(x, y)] = Some(Tile::stationary(
+            ElementState::default(SAND.id()),
+            Temperature::celsius(20),
+        ));
+    }
+    world.apply_gravity();
+    let velocity = |x, y| world[point(x, y)].as_ref().unwrap().velocity;
+    assert_eq!((velocity(100, 50).x, velocity(100, 50).y), (0, 1));
+    assert_eq!((velocity(150, 100).x, velocity(150, 100).y), (-1, 0));
+    assert_eq!((velocity(100, 150).x, velocity(100, 150).y), (0, -1));
+    assert_eq!((velocity(50, 100).x, velocity(50, 100).y), (1, 0));
+}
+
+#[test]
+fn sideways_gravity_rests_tiles_against_walls() {
+    use crate::simple_elements::{SAND, WALL};
+    use crate::temperature::Temperature;
+
+    let mut world = World::new(0);
+    world.settings_mut().gravity_direction = (-1.0, 0.0);
+    world[point(10, 10)] = Some(Tile::stationary(
+        ElementState::default(WALL.id()),
+        Temperature::celsius(20),
+    ));
+    world[point(11, 10)] = Some(Tile::stationary(
+        ElementState::default(SAND.id()),
+        Temperature::celsius(20),
+    ));
+    assert!(world.has_stable_floor(point(11, 10)));
+    world[point(11, 10)].as_mut().unwrap().velocity.x = 2;
+    world.pause_particles();
+    assert!(world[point(11, 10)].as_ref().unwrap().velocity.is_zero());
+    world[point(30, 10)] = world[point(11, 10)].clone();
+    assert!(!world.has_stable_floor(point(30, 10)));
+}
+
+#[test]
+fn glue_sticks_to_a_ceiling() {
+    use crate::glue::GLUE;
+    use crate::simple_elements::{SAND, WALL};
+    use crate::temperature::Temperature;
+
+    let mut world = World::new(0);
+    for &(x, element) in &[(10, &GLUE), (20, &SAND)] {
+        world[point(x, 10)] = Some(Tile::stationary(
+            ElementState::default(WALL.id()),
+            Temperature::celsius(20),
+        ));
+        world[point(x, 11)] = Some(Tile::stationary(
+            ElementState::default(element.id()),
+            Temperature::celsius(20),
+        ));
+    }
+    world.apply_gravity();
+    assert!(world[point(10, 11)].as_ref().unwrap().velocity.is_zero());
+    assert_eq!(world[point(20, 11)].as_ref().unwrap().velocity.y, 1);
+}
+
+#[test]
+fn liquid_levels_out_under_sideways_gravity() {
+    use crate::simple_elements::WALL;
+    use crate::water::WATER;
+
+    let mut world = crate::create_world();
+    world.settings_mut().gravity_direction = (1.0, 0.0);
+    crate::fill(&mut world, &WALL, 150..=150, 0..=199);
+    crate::fill(&mut world, &WATER, 120..=129, 90..=109);
+    crate::run(&mut world, 600);
+    // Water piles up against the wall and spreads along it
+    let rows: Vec<usize> = (0..WORLD_HEIGHT)
+        .map(|y| {
+            (0..150)
+                .filter(
+                    |&x| matches!(&world[point(x, y)], Some(tile) if tile.element_id() == WATER.id),
+                )
+                .count()
+        })
+        .collect();
+    assert_eq!(rows.iter().sum::<usize>(), 200);
+    assert!(rows.iter().all(|&depth| depth <= 3), "{:?}", rows);
+    assert!(
+        rows.iter().filter(|&&depth| depth > 0).count() > 120,
+        "{:?}",
+        rows
+    );
+}
+
+#[test]
+fn liquids_layer_by_density_under_sideways_gravity() {
+    use crate::oil::OIL;
+    use crate::simple_elements::WALL;
+    use crate::water::WATER;
+
+    let mut world = crate::create_world();
+    world.settings_mut().gravity_direction = (1.0, 0.0);
+    // A narrow tank on its side, with the lighter oil poured in nearest its floor
+    crate::fill(&mut world, &WALL, 10..=60, 10..=10);
+    crate::fill(&mut world, &WALL, 10..=60, 17..=17);
+    crate::fill(&mut world, &WALL, 60..=60, 10..=17);
+    crate::fill(&mut world, &WATER, 40..=49, 11..=16);
+    crate::fill(&mut world, &OIL, 50..=59, 11..=16);
+    crate::run(&mut world, 3000);
+    let depth = |element: &crate::element::Element| {
+        let xs: Vec<f64> = (0..WORLD_SIZE as usize)
+            .filter(|&i| matches!(&world[i], Some(tile) if tile.element_id() == element.id))
+            .map(|i| coords(i).0 as f64)
+            .collect();
+        xs.iter().sum::<f64>() / xs.len() as f64
+    };
+    assert!(depth(&OIL) + 2.0 < depth(&WATER));
+}
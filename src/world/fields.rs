@@ -0,0 +1,174 @@
+use super::World;
+use crate::element::Color;
+use crate::{coords, point, WORLD_HEIGHT, WORLD_SIZE, WORLD_WIDTH};
+
+/// Identifies one of the world's scalar fields
+pub type FieldId = usize;
+
+/// Glow given off by fire and lava, which every world has
+pub const LIGHT: FieldId = 0;
+pub(super) const LIGHT_RULES: FieldRules = FieldRules {
+    diffusion: 0.8,
+    decay: 0.25,
+    edge: FieldEdge::Fixed(0.0),
+    color: [1.0, 0.8, 0.4, 0.6],
+    overlay_scale: 0.5,
+};
+
+/// What a field does at the edges of the world
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum FieldEdge {
+    /// Nothing flows in or out
+    Closed,
+    /// Beyond the edge the field is held at this value, so it flows
+    /// out to it or in from it
+    Fixed(f64),
+    /// The field flows out of one side and back in on the other
+    Wrap,
+}
+
+/// How a field spreads out and fades away over time, and how it is drawn
+#[derive(Clone, Copy, Debug)]
+pub struct FieldRules {
+    /// Fraction of the difference between a cell and the average of the four
+    /// cells beside it that is evened out in each pass, from 0.0 to 1.0
+    pub diffusion: f64,
+    /// Fraction of the field that fades away in each pass, from 0.0 to 1.0
+    pub decay: f64,
+    pub edge: FieldEdge,
+    /// Color the field's overlay is drawn in
+    pub color: Color,
+    /// Value at which the overlay is drawn fully opaque
+    pub overlay_scale: f64,
+}
+
+impl Default for FieldRules {
+    fn default() -> Self {
+        FieldRules {
+            diffusion: 0.5,
+            decay: 0.0,
+            edge: FieldEdge::Closed,
+            color: [1.0, 1.0, 1.0, 1.0],
+            overlay_scale: 1.0,
+        }
+    }
+}
+
+impl World {
+    /// Adds a scalar field, such as oxygen or light, that covers every cell
+    /// of the world whether there is a tile in it or not. Starts at 0.0.
+    pub fn add_field(&mut self, name: &'static str, rules: FieldRules) -> FieldId {
+        self.field_info.push((name, rules));
+        self.fields.push(vec![0.0; WORLD_SIZE as usize]);
+        self.fields.len() - 1
+    }
+
+    pub fn field_count(&self) -> usize {
+        self.fields.len()
+    }
+
+    pub fn field_name(&self, field: FieldId) -> &'static str {
+        self.field_info[field].0
+    }
+
+    /// Values of a field, one for each cell of the world
+    pub fn field(&self, field: FieldId) -> &[f64] {
+        &self.fields[field]
+    }
+
+    pub fn field_mut(&mut self, field: FieldId) -> &mut [f64] {
+        &mut self.fields[field]
+    }
+
+    /// Color to draw over cell `i` to show `field`, fading out as the field does
+    pub fn field_overlay_color(&self, field: FieldId, i: usize) -> Color {
+        let rules = &self.field_info[field].1;
+        let [red, green, blue, alpha] = rules.color;
+        let strength = (self.fields[field][i] / rules.overlay_scale).clamp(0.0, 1.0);
+        [red, green, blue, alpha * strength as f32]
+    }
+
+    /// Spreads out and fades away every field by one pass of its rules
+    pub fn apply_field_diffusion(&mut self) {
+        let mut next = vec![0.0; WORLD_SIZE as usize];
+        for (values, (_, rules)) in self.fields.iter_mut().zip(&self.field_info) {
+            for (i, next) in next.iter_mut().enumerate() {
+                let (x, y) = coords(i);
+                let beside = |x: i32, y: i32| {
+                    if (0..WORLD_WIDTH).contains(&x) && (0..WORLD_HEIGHT).contains(&y) {
+                        return values[point(x, y)];
+                    }
+                    match rules.edge {
+                        FieldEdge::Closed => values[i],
+                        FieldEdge::Fixed(value) => value,
+                        FieldEdge::Wrap => {
+                            values[point(x.rem_euclid(WORLD_WIDTH), y.rem_euclid(WORLD_HEIGHT))]
+                        }
+                    }
+                };
+                let average =
+                    (beside(x - 1, y) + beside(x + 1, y) + beside(x, y - 1) + beside(x, y + 1))
+                        / 4.0;
+                let spread = values[i] + rules.diffusion * (average - values[i]);
+                *next = spread * (1.0 - rules.decay);
+            }
+            values.copy_from_slice(&next);
+        }
+    }
+}
+
+#[test]
+fn fields_spread_out_and_fade_away() {
+    let mut world = crate::create_world();
+    let light = world.add_field(
+        "light",
+        FieldRules {
+            decay: 0.01,
+            ..FieldRules::default()
+        },
+    );
+    world.field_mut(light)[point(100, 100)] = 100.0;
+    for _ in 0..20 {
+        world.apply_field_diffusion();
+    }
+    let field = world.field(light);
+    assert!(field[point(100, 100)] < 100.0);
+    assert!(field[point(103, 100)] > 0.0);
+    assert!((field[point(103, 100)] - field[point(97, 100)]).abs() < 1e-12);
+    let total: f64 = field.iter().sum();
+    assert!((total - 100.0 * 0.99f64.powi(20)).abs() < 1e-6);
+    assert_eq!(world.field_name(light), "light");
+}
+
+#[test]
+fn fields_are_held_at_a_fixed_edge() {
+    let mut world = crate::create_world();
+    let oxygen = world.add_field(
+        "oxygen",
+        FieldRules {
+            edge: FieldEdge::Fixed(1.0),
+            ..FieldRules::default()
+        },
+    );
+    for _ in 0..50 {
+        world.apply_field_diffusion();
+    }
+    let field = world.field(oxygen);
+    assert!(field[point(0, 100)] > field[point(5, 100)]);
+    assert!(field[point(5, 100)] > 0.0);
+    assert_eq!(field[point(100, 100)], 0.0);
+}
+
+#[test]
+fn reactions_light_up_the_world_through_their_view() {
+    use crate::fire::FIRE;
+
+    let mut world = crate::create_world();
+    world.settings_mut().threads = 2;
+    crate::fill(&mut world, &FIRE, 100..=100, 100..=100);
+    world.apply_periodic_reactions();
+    assert_eq!(world.field(LIGHT)[point(100, 100)], 1.0);
+    world.apply_field_diffusion();
+    assert!(world.field(LIGHT)[point(101, 100)] > 0.0);
+    assert_eq!(world.field(LIGHT)[point(150, 100)], 0.0);
+}
@@ -0,0 +1,271 @@
+use super::World;
+use crate::element::{Channel, COMPRESSION_PER_TILE, FIXED, GASEOUS};
+use crate::tile::Speed;
+use crate::{step_between, WORLD_SIZE};
+use rand::Rng;
+
+/// How much velocity a difference in pressure of 1.0 gives a tile of mass 1
+/// in each pressure pass
+const PRESSURE_FORCE: f64 = 4.0;
+
+/// Unit vector pointing from tile `from` to its neighbor `to`
+fn direction(from: usize, to: usize) -> (f64, f64) {
+    let (dx, dy) = step_between(from, to);
+    let (dx, dy) = (dx as f64, dy as f64);
+    let length = dx.hypot(dy);
+    (dx / length, dy / length)
+}
+
+impl World {
+    /// Pressure of the gas at `i` as of the last pressure pass, where 1.0 is
+    /// a tile's worth of gas at the ambient temperature in every cell around it.
+    /// Zero for empty cells and anything that isn't a gas.
+    pub fn pressure_at(&self, i: usize) -> f64 {
+        self.pressure[i]
+    }
+
+    /// Pushes gases from high to low pressure, and lets pressurized gas
+    /// push loose tiles out of its way and burst weak solids.
+    /// Only the gas in awake chunks, and the spaces it fills, is updated.
+    pub fn apply_gas_pressure(&mut self) {
+        let cells: Vec<usize> = self.awake_cells().collect();
+        self.pressure = self.space_pressures(&cells);
+
+        let mut rng = rand::thread_rng();
+        for &i in &cells {
+            let (force_x, force_y) = self.pressure_force(i);
+            let tile = match &mut self[i] {
+                Some(tile) => tile,
+                None => continue,
+            };
+            if tile.has_flag(FIXED) {
+                if force_x.hypot(force_y) > tile.get_element().burst_pressure {
+                    self[i] = None;
+                    self.wake(i);
+                }
+                continue;
+            }
+            let mass = tile.get_element().mass.max(1) as f64;
+            // Round up or down at random, so small forces still add up over time
+            let mut push = |force: f64| (PRESSURE_FORCE * force / mass + rng.gen::<f64>()).floor();
+            let (push_x, push_y) = (push(force_x), push(force_y));
+            let limit = |speed: f64| speed.clamp(Speed::MIN as f64, Speed::MAX as f64) as Speed;
+            tile.velocity.x = limit(tile.velocity.x as f64 + push_x);
+            tile.velocity.y = limit(tile.velocity.y as f64 + push_y);
+            if push_x != 0.0 || push_y != 0.0 {
+                self.wake(i);
+            }
+        }
+    }
+
+    /// Tiles' worth of gas at the ambient temperature that the tile at `i`
+    /// holds, counting any packed into it and how hot it is
+    fn gas_amount(&self, i: usize) -> f64 {
+        let tile = match &self[i] {
+            Some(tile) if tile.has_flag(GASEOUS) => tile,
+            _ => return 0.0,
+        };
+        let packed = tile.channel(Channel::Compression) as f64 / COMPRESSION_PER_TILE as f64;
+        let ambient = self.settings.ambient_temperature.kelvin().max(1) as f64;
+        (1.0 + packed) * tile.temperature.kelvin() as f64 / ambient
+    }
+
+    /// Pressure of the gas in each cell from all the gas in the space it is
+    /// in, the cells of gas and empty cells connected to it, spread evenly
+    /// over that space. Gas sealed in a container gets more pressurized the
+    /// more of it is packed in, while gas out in the open is hardly
+    /// pressurized at all. Zero for cells that aren't gas.
+    /// Only spaces reaching one of `starts` are measured; cells outside them
+    /// keep the pressure they had.
+    fn space_pressures(&self, starts: &[usize]) -> Vec<f64> {
+        let mut pressures = self.pressure.clone();
+        let mut seen = vec![false; WORLD_SIZE as usize];
+        let is_space = |j: usize| self[j].as_ref().is_none_or(|tile| tile.has_flag(GASEOUS));
+        for &start in starts {
+            pressures[start] = 0.0;
+        }
+        for &start in starts {
+            if seen[start] || self[start].is_none() || !is_space(start) {
+                continue;
+            }
+            seen[start] = true;
+            let mut space = vec![start];
+            let mut next = 0;
+            while let Some(&i) = space.get(next) {
+                next += 1;
+                for j in self.neighbors(i) {
+                    if !seen[j] && is_space(j) {
+                        seen[j] = true;
+                        space.push(j);
+                    }
+                }
+            }
+            let amount: f64 = space.iter().map(|&i| self.gas_amount(i)).sum();
+            let pressure = amount / space.len() as f64;
+            for &i in &space {
+                // Gas bunched up out in the open is still pushed apart by the
+                // gas right around it, though its space is hardly pressurized
+                pressures[i] = match self[i] {
+                    Some(_) => pressure.max(self.gas_pressure(i)),
+                    None => 0.0,
+                };
+            }
+        }
+        pressures
+    }
+
+    /// Gas pressure at `i`, from how much of the open space around it is
+    /// filled with gas, and how hot that gas is compared to the environment.
+    fn gas_pressure(&self, i: usize) -> f64 {
+        let temperature = match &self[i] {
+            Some(tile) if tile.has_flag(GASEOUS) => tile.temperature,
+            _ => return 0.0,
+        };
+        let (mut gas, mut open) = (1, 1);
+        for j in self.neighbors(i) {
+            match &self[j] {
+                None => open += 1,
+                Some(tile) if tile.has_flag(GASEOUS) => {
+                    gas += 1;
+                    open += 1;
+                }
+                Some(_) => {}
+            }
+        }
+        let ambient = self.settings.ambient_temperature.kelvin().max(1) as f64;
+        gas as f64 / open as f64 * temperature.kelvin() as f64 / ambient
+    }
+
+    /// Net force the surrounding gas puts on the tile at `i`.
+    /// Gas flows freely into empty cells, while solids and liquids only feel
+    /// pressure above that of the surrounding air.
+    fn pressure_force(&self, i: usize) -> (f64, f64) {
+        let tile = match &self[i] {
+            Some(tile) => tile,
+            None => return (0.0, 0.0),
+        };
+        let is_gas = tile.has_flag(GASEOUS);
+        let (mut force_x, mut force_y) = (0.0, 0.0);
+        for j in self.neighbors(i) {
+            let difference = match &self[j] {
+                None if is_gas => self.pressure[i],
+                Some(neighbor) if is_gas && neighbor.has_flag(GASEOUS) => {
+                    self.pressure[i] - self.pressure[j]
+                }
+                Some(neighbor) if !is_gas && neighbor.has_flag(GASEOUS) => {
+                    -(self.pressure[j] - 1.0).max(0.0)
+                }
+                _ => continue,
+            };
+            let (dx, dy) = direction(i, j);
+            force_x += difference * dx;
+            force_y += difference * dy;
+        }
+        (force_x, force_y)
+    }
+}
+
+#[cfg(test)]
+fn sealed_glass_box(world: &mut World, gas_temperature: crate::Temperature) {
+    use crate::glass::GLASS;
+    use crate::{gas::GAS, point, ElementState, Tile};
+
+    for x in 10..=20 {
+        for y in 10..=20 {
+            let element = if x == 10 || x == 20 || y == 10 || y == 20 {
+                &GLASS
+            } else {
+                &GAS
+            };
+            world[point(x, y)] = Some(Tile::stationary(
+                ElementState::default(element.id()),
+                gas_temperature,
+            ));
+        }
+    }
+}
+
+#[cfg(test)]
+fn glass_remaining(world: &World) -> usize {
+    use crate::glass::GLASS;
+
+    (0..WORLD_SIZE as usize)
+        .filter(|&i| {
+            world[i]
+                .as_ref()
+                .is_some_and(|t| t.element_id() == GLASS.id)
+        })
+        .count()
+}
+
+#[test]
+fn glass_holds_gas_at_ambient_temperature() {
+    use crate::Temperature;
+
+    let mut world = crate::create_world();
+    sealed_glass_box(&mut world, Temperature::celsius(20));
+    for _ in 0..10 {
+        world.apply_gas_pressure();
+    }
+    let glass = glass_remaining(&world);
+    assert_eq!(glass, 40);
+}
+
+#[test]
+fn packing_more_gas_into_glass_bursts_it() {
+    use crate::{point, Temperature};
+
+    let mut world = crate::create_world();
+    sealed_glass_box(&mut world, Temperature::celsius(20));
+    for x in 11..=19 {
+        for y in 11..=19 {
+            let gas = world[point(x, y)].as_mut().unwrap();
+            gas.set_channel(Channel::Compression, COMPRESSION_PER_TILE);
+            gas.save_state();
+        }
+    }
+    world.apply_gas_pressure();
+    assert!((world.pressure_at(point(15, 15)) - 2.0).abs() < 1e-9);
+    let glass = glass_remaining(&world);
+    assert!(glass < 40);
+}
+
+#[test]
+fn hot_gas_bursts_glass() {
+    use crate::Temperature;
+
+    let mut world = crate::create_world();
+    sealed_glass_box(&mut world, Temperature::celsius(400));
+    world.apply_gas_pressure();
+    let glass = glass_remaining(&world);
+    assert!(glass < 40);
+}
+
+#[test]
+fn hot_gas_pushes_a_plug_of_sand() {
+    use crate::simple_elements::{SAND, WALL};
+    use crate::{gas::GAS, point, ElementState, Temperature, Tile};
+
+    let mut world = crate::create_world();
+    // A narrow tube, plugged with sand at the top and filled with hot gas below
+    for y in 10..=21 {
+        for x in 49..=51 {
+            let (element, temperature) = if x != 50 || y == 21 {
+                (&WALL, 20)
+            } else if y < 13 {
+                (&SAND, 20)
+            } else {
+                (&GAS, 400)
+            };
+            world[point(x, y)] = Some(Tile::stationary(
+                ElementState::default(element.id()),
+                Temperature::celsius(temperature),
+            ));
+        }
+    }
+    for _ in 0..10 {
+        world.apply_gas_pressure();
+    }
+    let plug = world[point(50, 12)].as_ref().unwrap();
+    assert!(plug.velocity.y < 0);
+}
@@ -0,0 +1,393 @@
+use super::scan::order_row;
+use super::sleep::{CHUNKS_HIGH, CHUNKS_WIDE, CHUNK_SIZE};
+use super::{Boundary, World, WorldSettings};
+use crate::element::{Channel, PeriodicReaction};
+use crate::tile::{ElementState, Tile};
+use crate::world_view::{window_cell, CellRng, NeighborhoodView};
+use crate::{cell_within, point, WORLD_HEIGHT, WORLD_SIZE, WORLD_WIDTH};
+use itertools::iproduct;
+use rand::Rng;
+use std::sync::mpsc;
+use std::thread;
+
+/// A chunk of the world copied out along with a border one cell wide,
+/// so that its reactions can run at the same time as those of other chunks
+struct Window {
+    /// World coordinates of the top left cell, border included
+    origin: (i32, i32),
+    width: i32,
+    cells: Vec<Option<Tile>>,
+    /// Values of each scalar field in the same cells
+    fields: Vec<Vec<f64>>,
+    /// Tiles that reactions placed in or took out of cells of the world,
+    /// which wait until every tile has reacted, as the cell, the cell of
+    /// the tile whose reaction it was, and what the cell now holds
+    edits: Vec<(usize, usize, Option<Tile>)>,
+}
+
+/// Element and info of the tile in `cell`, if there is one
+fn state_of(cell: &Option<Tile>) -> Option<ElementState> {
+    cell.as_ref().map(|tile| *tile.get_state())
+}
+
+/// Which of the passes over the chunks the chunk at `chunk` along an axis
+/// `chunks` long reacts in. Neighboring chunks never react in the same pass,
+/// so chunks in a pass can't reach into each other.
+fn phase(chunk: i32, chunks: i32, wrap: bool) -> i32 {
+    // With an odd number of chunks, the last chunk of a wrapping world
+    // touches the first one, so it gets a pass of its own
+    if wrap && chunks % 2 == 1 && chunk == chunks - 1 {
+        2
+    } else {
+        chunk % 2
+    }
+}
+
+/// Copies the chunk at `(chunk_x, chunk_y)` out of `grid` and `fields`,
+/// along with a border one cell wide
+fn copy_window(
+    grid: &[Option<Tile>],
+    fields: &[Vec<f64>],
+    (chunk_x, chunk_y): (i32, i32),
+    boundary: Boundary,
+) -> Window {
+    let (left, top) = (chunk_x * CHUNK_SIZE, chunk_y * CHUNK_SIZE);
+    let right = (left + CHUNK_SIZE).min(WORLD_WIDTH);
+    let bottom = (top + CHUNK_SIZE).min(WORLD_HEIGHT);
+    let cells = iproduct!(top - 1..=bottom, left - 1..=right)
+        .map(|(y, x)| cell_within(x, y, boundary).and_then(|i| grid[i].clone()))
+        .collect();
+    let fields = fields
+        .iter()
+        .map(|field| {
+            iproduct!(top - 1..=bottom, left - 1..=right)
+                .map(|(y, x)| cell_within(x, y, boundary).map_or(0.0, |i| field[i]))
+                .collect()
+        })
+        .collect();
+    Window {
+        origin: (left - 1, top - 1),
+        width: right - left + 2,
+        cells,
+        fields,
+        edits: Vec::new(),
+    }
+}
+
+/// Runs the periodic reactions of every tile inside the border of `window`, in order
+fn react_in_window(window: &mut Window, settings: &WorldSettings, tick: u64, seed: u64) {
+    let boundary = settings.boundary;
+    let Window {
+        origin,
+        width,
+        cells,
+        fields,
+        edits,
+    } = window;
+    let (origin, width) = (*origin, *width);
+    let height = cells.len() as i32 / width;
+    let (left, top) = (origin.0 + 1, origin.1 + 1);
+    let (right, bottom) = (origin.0 + width - 1, origin.1 + height - 1);
+    let rows = (top..bottom).flat_map(|y| {
+        let mut xs: Vec<i32> = (left..right).collect();
+        order_row(settings.scan_order, settings.seed, tick, y, &mut xs);
+        xs.into_iter().map(move |x| (y, x))
+    });
+    for (y, x) in rows {
+        let local = ((x - origin.0) + (y - origin.1) * width) as usize;
+        let tile = match cells[local].clone() {
+            Some(tile) => tile,
+            None => continue,
+        };
+        let before = tile.clone();
+        let source = point(x, y);
+        // Cells around the tile as they were before it reacted
+        let around: Vec<(usize, usize, Option<Tile>)> = match tile.get_element().periodic_reaction {
+            PeriodicReaction::Some(_) => iproduct!(-1..=1, -1..=1)
+                .filter(|&step| step != (0, 0))
+                .filter_map(|(dx, dy)| {
+                    let i = cell_within(x + dx, y + dy, boundary)?;
+                    let local = window_cell((x, y), origin, width, boundary, (dx, dy))?;
+                    Some((local, i, cells[local].clone()))
+                })
+                .collect(),
+            _ => Vec::new(),
+        };
+        let rng = CellRng::new(seed.wrapping_add(source as u64));
+        let mut view =
+            NeighborhoodView::within(cells, fields, (x, y), origin, width, boundary, rng);
+        let result = match tile.get_element().periodic_reaction {
+            PeriodicReaction::Some(reaction) => reaction(tile, view),
+            PeriodicReaction::None => continue,
+            PeriodicReaction::DecayInto {
+                element_id,
+                lifetime,
+                rarity,
+            } => {
+                if view.rng().gen_range(0, rarity) != 0 {
+                    continue;
+                }
+                let mut new_tile = tile.clone();
+                let age = tile.channel(Channel::Age).saturating_add(1);
+                new_tile.set_channel(Channel::Age, age);
+                if age == lifetime {
+                    new_tile.set_element(element_id)
+                }
+                Some(new_tile)
+            }
+            PeriodicReaction::DecayToNothing { lifetime, rarity } => {
+                if view.rng().gen_range(0, rarity) != 0 {
+                    continue;
+                }
+                let mut new_tile = tile.clone();
+                let age = tile.channel(Channel::Age).saturating_add(1);
+                new_tile.set_channel(Channel::Age, age);
+                if age == lifetime {
+                    None
+                } else {
+                    Some(new_tile)
+                }
+            }
+        };
+        // Nothing the reaction did shows until every tile has reacted, so the
+        // order tiles react in doesn't matter. Tiles placed in or taken out
+        // of cells, or swapped for other elements, wait in `edits`.
+        for (local, i, original) in around {
+            if state_of(&cells[local]) != state_of(&original) {
+                let placed = std::mem::replace(&mut cells[local], original);
+                edits.push((i, source, placed));
+            }
+        }
+        match result {
+            Some(mut after) if after.get_state() == before.get_state() => {
+                after.stage_changes_since(&before);
+                cells[local] = Some(after);
+            }
+            result => edits.push((source, source, result)),
+        }
+    }
+}
+
+impl World {
+    /// Runs the periodic reaction of every awake tile. The world is split
+    /// into chunks that react in a checkerboard pattern, spread over
+    /// `settings.threads` threads, and each tile draws its random numbers
+    /// from `settings.seed`, so the outcome doesn't depend on the thread count.
+    pub fn apply_periodic_reactions(&mut self) {
+        self.reaction_pass = self.reaction_pass.wrapping_add(1);
+        let threads = self.settings.threads.max(1);
+        let seed = self
+            .settings
+            .seed
+            .wrapping_add(self.reaction_pass.wrapping_mul(WORLD_SIZE as u64));
+        let settings = self.settings.clone();
+        let (settings, tick) = (&settings, self.tick);
+        let (mut edits, touched) = if threads == 1 {
+            self.react_in_phases(|windows| {
+                for window in windows.iter_mut() {
+                    react_in_window(window, settings, tick, seed);
+                }
+            })
+        } else {
+            // The same workers react in every pass over the chunks, each
+            // taking its share of the windows and handing them back
+            thread::scope(|scope| {
+                let workers: Vec<_> = (0..threads)
+                    .map(|_| {
+                        let (send_windows, windows) = mpsc::channel::<Vec<Window>>();
+                        let (send_reacted, reacted) = mpsc::channel();
+                        scope.spawn(move || {
+                            for mut share in windows {
+                                for window in share.iter_mut() {
+                                    react_in_window(window, settings, tick, seed);
+                                }
+                                if send_reacted.send(share).is_err() {
+                                    break;
+                                }
+                            }
+                        });
+                        (send_windows, reacted)
+                    })
+                    .collect();
+                self.react_in_phases(|windows| {
+                    let mut shares: Vec<Vec<Window>> = (0..threads).map(|_| Vec::new()).collect();
+                    for (k, window) in windows.drain(..).enumerate() {
+                        shares[k % threads].push(window);
+                    }
+                    for ((send_windows, _), share) in workers.iter().zip(shares) {
+                        send_windows.send(share).expect("reaction worker panicked");
+                    }
+                    for (_, reacted) in &workers {
+                        windows.extend(reacted.recv().expect("reaction worker panicked"));
+                    }
+                })
+            })
+        };
+        self.reacting
+            .iter_mut()
+            .for_each(|reacting| *reacting = false);
+        // Window borders reach into sleeping chunks, so commit every cell a
+        // window covered, not only the awake ones
+        for i in touched {
+            if let Some(tile) = &mut self[i] {
+                let changed = tile.has_staged_changes();
+                tile.save_state();
+                if changed {
+                    self.mark_reacting(i);
+                }
+            }
+        }
+        // Where reactions put more than one thing in a cell, the one from the
+        // last tile in the grid wins, whatever order the tiles reacted in
+        edits.sort_by_key(|&(i, source, _)| (i, source));
+        for (i, _, tile) in edits {
+            self[i] = tile.map(|mut tile| {
+                tile.save_state();
+                tile
+            });
+            self.mark_reacting(i);
+        }
+    }
+
+    /// Copies out the awake chunks of each pass of the checkerboard, has
+    /// `react` run their reactions, and copies them back into the world.
+    /// Returns the edits the reactions left waiting, and every cell the
+    /// windows covered, borders included.
+    #[allow(clippy::type_complexity)]
+    fn react_in_phases(
+        &mut self,
+        mut react: impl FnMut(&mut Vec<Window>),
+    ) -> (Vec<(usize, usize, Option<Tile>)>, Vec<usize>) {
+        let mut edits = Vec::new();
+        let mut touched = Vec::new();
+        let boundary = self.settings.boundary;
+        let wrap = boundary == Boundary::Wrap;
+        for (phase_y, phase_x) in iproduct!(0..3, 0..3) {
+            let mut windows: Vec<Window> = iproduct!(0..CHUNKS_HIGH, 0..CHUNKS_WIDE)
+                .map(|(chunk_y, chunk_x)| (chunk_x, chunk_y))
+                .filter(|&(chunk_x, chunk_y)| {
+                    phase(chunk_x, CHUNKS_WIDE, wrap) == phase_x
+                        && phase(chunk_y, CHUNKS_HIGH, wrap) == phase_y
+                        && self.awake[(chunk_x + chunk_y * CHUNKS_WIDE) as usize]
+                })
+                .map(|chunk| copy_window(self.grid.as_ref(), &self.fields, chunk, boundary))
+                .collect();
+            if windows.is_empty() {
+                continue;
+            }
+            react(&mut windows);
+            // Windows of the same pass don't overlap, so the order they
+            // are copied back in doesn't matter
+            for window in windows {
+                edits.extend(window.edits);
+                let height = window.cells.len() as i32 / window.width;
+                let cells = iproduct!(0..height, 0..window.width).zip(window.cells);
+                for (local, ((y, x), cell)) in cells.enumerate() {
+                    let (x, y) = (window.origin.0 + x, window.origin.1 + y);
+                    if let Some(i) = cell_within(x, y, boundary) {
+                        touched.push(i);
+                        self.grid[i] = cell;
+                        for (field, values) in self.fields.iter_mut().zip(&window.fields) {
+                            field[i] = values[local];
+                        }
+                    }
+                }
+            }
+        }
+        touched.sort_unstable();
+        touched.dedup();
+        (edits, touched)
+    }
+}
+
+/// Element, info, moisture, temperature and velocity of every cell of a scene
+/// of burning oil, sand, water, a comb of lava and wet and dry dirt after
+/// 50 passes of periodic reactions
+#[cfg(test)]
+#[allow(clippy::type_complexity)]
+fn react_in_test_scene(
+    configure: impl Fn(&mut WorldSettings),
+) -> Vec<Option<(u8, u8, u8, crate::temperature::Temperature, (i32, i32))>> {
+    use crate::simple_elements::{SAND, WALL};
+    use crate::{dirt::DIRT, fire::FIRE, lava::LAVA, oil::OIL, water::WATER};
+
+    let mut world = crate::create_world();
+    world.settings_mut().seed = 7;
+    configure(world.settings_mut());
+    crate::fill(&mut world, &WALL, 0..=199, 199..=199);
+    crate::fill(&mut world, &SAND, 10..=60, 150..=198);
+    crate::fill(&mut world, &OIL, 70..=130, 150..=198);
+    crate::fill(&mut world, &WATER, 140..=190, 150..=198);
+    crate::fill(&mut world, &FIRE, 70..=130, 140..=149);
+    // A checkerboard of lava, with gaps that the tiles on either side
+    // can both sprinkle fire into
+    for (x, y) in iproduct!(10..=60, 90..=110) {
+        if (x + y) % 2 == 0 {
+            crate::fill(&mut world, &LAVA, x..=x, y..=y);
+            world[point(x, y)].as_mut().unwrap().temperature = LAVA.default_temperature;
+        }
+    }
+    // Dirt that gets drier from left to right, so moisture spreads sideways
+    crate::fill(&mut world, &DIRT, 140..=190, 100..=110);
+    for x in 140..=190 {
+        for y in 100..=110 {
+            let tile = world[point(x, y)].as_mut().unwrap();
+            tile.set_channel(Channel::Moisture, (190 - x) as u8 * 5);
+            tile.save_state();
+        }
+    }
+    for _ in 0..50 {
+        world.apply_periodic_reactions();
+    }
+    (0..WORLD_SIZE as usize)
+        .map(|i| {
+            world[i].as_ref().map(|tile| {
+                (
+                    tile.element_id(),
+                    tile.special_info(),
+                    tile.channel(Channel::Moisture),
+                    tile.temperature,
+                    (tile.velocity.x as i32, tile.velocity.y as i32),
+                )
+            })
+        })
+        .collect()
+}
+
+#[test]
+fn reactions_are_the_same_whatever_the_thread_count() {
+    let one_thread = react_in_test_scene(|settings| settings.threads = 1);
+    let four_threads = react_in_test_scene(|settings| settings.threads = 4);
+    assert!(one_thread == four_threads);
+}
+
+#[test]
+fn reactions_are_the_same_whatever_order_tiles_react_in() {
+    use super::ScanOrder;
+
+    let forward = react_in_test_scene(|settings| settings.scan_order = ScanOrder::Forward);
+    let shuffled = react_in_test_scene(|settings| settings.scan_order = ScanOrder::Shuffled);
+    assert!(forward == shuffled);
+}
+
+#[test]
+fn reactions_commit_changes_to_sleeping_neighbors() {
+    use crate::dirt::DIRT;
+
+    let mut world = crate::create_world();
+    // Wet dirt on the edge of a sleeping chunk, beside dry dirt in an awake one
+    crate::fill(&mut world, &DIRT, 15..=16, 100..=100);
+    let (wet, dry) = (point(15, 100), point(16, 100));
+    let tile = world[wet].as_mut().unwrap();
+    tile.set_channel(Channel::Moisture, 200);
+    tile.save_state();
+    world.awake.iter_mut().for_each(|awake| *awake = false);
+    world.awake[(1 + 100 / CHUNK_SIZE * CHUNKS_WIDE) as usize] = true;
+    assert!(!world.is_awake(wet) && world.is_awake(dry));
+
+    world.apply_periodic_reactions();
+    let wet = world[wet].as_ref().unwrap();
+    assert!(!wet.has_staged_changes());
+    assert!(wet.channel(Channel::Moisture) < 200);
+    assert!(world[dry].as_ref().unwrap().channel(Channel::Moisture) > 0);
+}
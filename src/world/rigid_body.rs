@@ -0,0 +1,450 @@
+use super::World;
+use crate::element::{FIXED, FLUID, GASEOUS};
+use crate::tile::{elastic_collide, Speed};
+use crate::{coords, in_bounds, point, MIN_POSITION, POSITIONS_PER_CELL, WORLD_SIZE};
+use std::collections::{HashMap, HashSet};
+use std::f64::consts::FRAC_PI_2;
+
+pub type BodyId = u32;
+
+/// Most tiles `bind_connected` gathers into one body
+const MAX_BODY_TILES: usize = 1024;
+/// Impact speed, in tile velocity units, that breaks a new body apart
+const DEFAULT_BODY_STRENGTH: f64 = 60.0;
+/// Fraction of a body's spin left after each tick
+const SPIN_DAMPING: f64 = 0.98;
+
+/// A group of tiles that moves as one object. Its tiles are marked with
+/// the body's id, and still feel forces like gravity and collisions one by
+/// one, but each tick those are pooled into a single velocity for the body.
+pub struct RigidBody {
+    /// Where the body is within its cell, like `Tile::position`
+    position: (f64, f64),
+    /// Rotation speed in quarter turns per tick, clockwise
+    spin: f64,
+    /// Rotation built up since the last quarter turn, in quarter turns
+    angle: f64,
+    /// Impact speed that breaks the body apart into loose tiles
+    pub strength: f64,
+}
+
+fn to_speed(value: f64) -> Speed {
+    value.round().clamp(Speed::MIN as f64, Speed::MAX as f64) as Speed
+}
+
+impl World {
+    /// Binds the tiles at `cells` into one rigid body. Fails unless there are
+    /// at least two of them, and all are loose solids that aren't part of a
+    /// body already.
+    pub fn make_rigid_body(&mut self, cells: &[usize]) -> Option<BodyId> {
+        let bindable = cells.len() >= 2
+            && cells.iter().all(|&i| match &self[i] {
+                Some(tile) => tile.body.is_none() && !tile.has_flag(FIXED | FLUID | GASEOUS),
+                None => false,
+            });
+        if !bindable {
+            return None;
+        }
+        let id = self.next_body_id;
+        self.next_body_id = self.next_body_id.wrapping_add(1);
+        let (velocity_x, velocity_y) = self.body_velocity(cells);
+        for &i in cells {
+            let tile = self[i].as_mut().unwrap();
+            tile.body = Some(id);
+            tile.velocity.x = to_speed(velocity_x);
+            tile.velocity.y = to_speed(velocity_y);
+            self.wake(i);
+        }
+        self.bodies.insert(
+            id,
+            RigidBody {
+                position: (0.0, 0.0),
+                spin: 0.0,
+                angle: 0.0,
+                strength: DEFAULT_BODY_STRENGTH,
+            },
+        );
+        Some(id)
+    }
+
+    /// Binds the tile at `i`, and every tile of the same element connected
+    /// to it, into one rigid body
+    pub fn bind_connected(&mut self, i: usize) -> Option<BodyId> {
+        let element_id = self[i].as_ref()?.element_id();
+        let mut cells = vec![i];
+        let mut visited: HashSet<usize> = cells.iter().copied().collect();
+        let mut next = 0;
+        while next < cells.len() && cells.len() < MAX_BODY_TILES {
+            let (x, y) = coords(cells[next]);
+            next += 1;
+            for &(dx, dy) in &[(0, -1), (-1, 0), (1, 0), (0, 1)] {
+                let (x, y) = (x + dx, y + dy);
+                if !in_bounds(x, y) || !visited.insert(point(x, y)) {
+                    continue;
+                }
+                match &self[point(x, y)] {
+                    Some(tile) if tile.element_id() == element_id && tile.body.is_none() => {
+                        cells.push(point(x, y));
+                    }
+                    _ => {}
+                }
+            }
+        }
+        cells.truncate(MAX_BODY_TILES);
+        self.make_rigid_body(&cells)
+    }
+
+    /// Lets the tiles of a body go, to move on their own again
+    pub fn break_rigid_body(&mut self, id: BodyId) {
+        self.bodies.remove(&id);
+        for tile in self.grid.iter_mut().flatten() {
+            if tile.body == Some(id) {
+                tile.body = None;
+            }
+        }
+    }
+
+    pub fn rigid_body_mut(&mut self, id: BodyId) -> Option<&mut RigidBody> {
+        self.bodies.get_mut(&id)
+    }
+
+    /// Moves and turns every rigid body by its pooled velocity and spin
+    pub fn apply_rigid_bodies(&mut self) {
+        if self.bodies.is_empty() {
+            return;
+        }
+        let mut members: HashMap<BodyId, Vec<usize>> = HashMap::new();
+        for i in 0..WORLD_SIZE as usize {
+            if let Some(tile) = &mut self.grid[i] {
+                if let Some(id) = tile.body {
+                    // Tiles that have melted or caught fire leave their body
+                    if !self.bodies.contains_key(&id) || tile.has_flag(FIXED | FLUID | GASEOUS) {
+                        tile.body = None;
+                    } else {
+                        members.entry(id).or_default().push(i);
+                    }
+                }
+            }
+        }
+        let mut ids: Vec<BodyId> = self.bodies.keys().copied().collect();
+        ids.sort_unstable();
+        for id in ids {
+            match members.remove(&id) {
+                Some(tiles) if tiles.len() >= 2 => self.update_body(id, tiles),
+                _ => self.break_rigid_body(id),
+            }
+        }
+    }
+
+    /// Mass-weighted average velocity of the tiles at `cells`
+    fn body_velocity(&self, cells: &[usize]) -> (f64, f64) {
+        let (mut momentum_x, mut momentum_y, mut mass) = (0.0, 0.0, 0.0);
+        for tile in cells.iter().filter_map(|&i| self[i].as_ref()) {
+            let tile_mass = tile.get_element().mass.max(1) as f64;
+            momentum_x += tile_mass * tile.velocity.x as f64;
+            momentum_y += tile_mass * tile.velocity.y as f64;
+            mass += tile_mass;
+        }
+        (momentum_x / mass, momentum_y / mass)
+    }
+
+    /// Total mass of the tiles at `cells`, and their center of mass
+    fn body_mass(&self, cells: &[usize]) -> (f64, (f64, f64)) {
+        let (mut mass, mut center_x, mut center_y) = (0.0, 0.0, 0.0);
+        for &i in cells {
+            let tile_mass = self[i].as_ref().unwrap().get_element().mass.max(1) as f64;
+            let (x, y) = coords(i);
+            mass += tile_mass;
+            center_x += tile_mass * x as f64;
+            center_y += tile_mass * y as f64;
+        }
+        (mass, (center_x / mass, center_y / mass))
+    }
+
+    fn update_body(&mut self, id: BodyId, mut tiles: Vec<usize>) {
+        // Forces on each tile since the last tick show up in its own velocity
+        let mut velocity = self.body_velocity(&tiles);
+        let body = &mut self.bodies.get_mut(&id).unwrap();
+        let advance = |position: f64, velocity: f64| {
+            let total = position + velocity;
+            let cells = ((total - MIN_POSITION as f64) / POSITIONS_PER_CELL as f64).floor();
+            (cells as i32, total - cells * POSITIONS_PER_CELL as f64)
+        };
+        let (cells_x, position_x) = advance(body.position.0, velocity.0);
+        let (cells_y, position_y) = advance(body.position.1, velocity.1);
+        body.position = (position_x, position_y);
+        body.angle += body.spin;
+        body.spin *= SPIN_DAMPING;
+        let quarter_turn = if body.angle.abs() >= 1.0 {
+            let direction = body.angle.signum();
+            body.angle -= direction;
+            direction as i32
+        } else {
+            0
+        };
+
+        // Move a cell at a time, stopping at the first collision
+        let steps = cells_x.abs().max(cells_y.abs());
+        let along =
+            |cells: i32, step: i32| (cells as f64 * step as f64 / steps as f64).round() as i32;
+        for step in 1..=steps {
+            let offset = (
+                along(cells_x, step) - along(cells_x, step - 1),
+                along(cells_y, step) - along(cells_y, step - 1),
+            );
+            if !self.translate_body(&mut tiles, offset) {
+                velocity = self.collide_body(id, &tiles, offset, velocity);
+                break;
+            }
+        }
+        if !self.bodies.contains_key(&id) {
+            // Broken apart by the collision
+            return;
+        }
+        if quarter_turn != 0 && !self.rotate_body(&mut tiles, quarter_turn) {
+            let body = self.bodies.get_mut(&id).unwrap();
+            body.spin = 0.0;
+            body.angle = 0.0;
+        }
+        for &i in &tiles {
+            let tile = self[i].as_mut().unwrap();
+            tile.velocity.x = to_speed(velocity.0);
+            tile.velocity.y = to_speed(velocity.1);
+            self.wake(i);
+        }
+    }
+
+    /// Moves the tiles of a body by `offset`, if there's room, pushing aside
+    /// lighter fluids in the way. Returns whether it moved. Bodies treat the
+    /// edges of the world as solid, whatever its boundary mode.
+    fn translate_body(&mut self, tiles: &mut Vec<usize>, (dx, dy): (i32, i32)) -> bool {
+        let destinations: Option<Vec<usize>> = tiles
+            .iter()
+            .map(|&i| {
+                let (x, y) = coords(i);
+                Some(point(x + dx, y + dy)).filter(|_| in_bounds(x + dx, y + dy))
+            })
+            .collect();
+        match destinations {
+            Some(destinations) => self.relocate_body(tiles, destinations),
+            None => false,
+        }
+    }
+
+    /// Turns a body a quarter turn about its center of mass, clockwise if
+    /// `direction` is positive. Returns whether there was room to turn.
+    fn rotate_body(&mut self, tiles: &mut Vec<usize>, direction: i32) -> bool {
+        let (_, (center_x, center_y)) = self.body_mass(tiles);
+        let (center_x, center_y) = (center_x.round() as i32, center_y.round() as i32);
+        let destinations: Option<Vec<usize>> = tiles
+            .iter()
+            .map(|&i| {
+                let (x, y) = coords(i);
+                let (dx, dy) = (x - center_x, y - center_y);
+                let (x, y) = (center_x - direction * dy, center_y + direction * dx);
+                Some(point(x, y)).filter(|_| in_bounds(x, y))
+            })
+            .collect();
+        match destinations {
+            Some(destinations) => self.relocate_body(tiles, destinations),
+            None => false,
+        }
+    }
+
+    /// Moves each tile of a body to the matching cell of `destinations`,
+    /// if all of them are empty, part of the body already, or hold a fluid
+    /// lighter than the body. Displaced fluid fills the cells left behind.
+    fn relocate_body(&mut self, tiles: &mut Vec<usize>, destinations: Vec<usize>) -> bool {
+        let (mass, _) = self.body_mass(tiles);
+        let density = mass / tiles.len() as f64;
+        let members: HashSet<usize> = tiles.iter().copied().collect();
+        let room = destinations.iter().all(|&j| {
+            members.contains(&j)
+                || match &self[j] {
+                    None => true,
+                    Some(tile) => {
+                        tile.has_flag(FLUID) && (tile.get_element().mass as f64) < density
+                    }
+                }
+        });
+        if !room {
+            return false;
+        }
+        let moving: Vec<_> = tiles.iter().map(|&i| self[i].take()).collect();
+        let displaced: Vec<_> = destinations
+            .iter()
+            .filter_map(|&j| self[j].take())
+            .collect();
+        let targets: HashSet<usize> = destinations.iter().copied().collect();
+        let vacated = tiles.iter().filter(|i| !targets.contains(i));
+        for (tile, &j) in moving.into_iter().zip(&destinations) {
+            self[j] = tile;
+        }
+        for (tile, &j) in displaced.into_iter().zip(vacated) {
+            self[j] = Some(tile);
+        }
+        for &i in tiles.iter().chain(&destinations) {
+            self.wake(i);
+        }
+        *tiles = destinations;
+        true
+    }
+
+    /// Bounces a body with `velocity` off whatever stopped it moving by `offset`,
+    /// as a single object. Returns the body's new velocity.
+    fn collide_body(
+        &mut self,
+        id: BodyId,
+        tiles: &[usize],
+        offset: (i32, i32),
+        velocity: (f64, f64),
+    ) -> (f64, f64) {
+        let length = (offset.0 as f64).hypot(offset.1 as f64);
+        let normal = (offset.0 as f64 / length, offset.1 as f64 / length);
+        let speed = velocity.0 * normal.0 + velocity.1 * normal.1;
+        let members: HashSet<usize> = tiles.iter().copied().collect();
+        let element = self[tiles[0]].as_ref().unwrap().get_element();
+        let (mass, (center_x, center_y)) = self.body_mass(tiles);
+
+        // Members on the leading edge, and whatever is in their way
+        let mut contacts = vec![];
+        let mut fixed = None;
+        let mut blockers = vec![];
+        for &i in tiles {
+            let (x, y) = coords(i);
+            let (x, y) = (x + offset.0, y + offset.1);
+            if !in_bounds(x, y) {
+                contacts.push(i);
+                fixed = fixed.or(Some(element));
+                continue;
+            }
+            let j = point(x, y);
+            if members.contains(&j) {
+                continue;
+            }
+            if let Some(tile) = &self[j] {
+                contacts.push(i);
+                if tile.has_flag(FIXED) {
+                    fixed = Some(tile.get_element());
+                } else {
+                    blockers.push(j);
+                }
+            }
+        }
+
+        let (new_speed, impact) = match fixed {
+            Some(surface) => (-speed * element.contact_restitution(surface), speed),
+            None => {
+                let blocker_mass: f64 = blockers
+                    .iter()
+                    .map(|&j| self[j].as_ref().unwrap().get_element().mass.max(1) as f64)
+                    .sum();
+                let blocker_speed = blockers
+                    .iter()
+                    .map(|&j| {
+                        let tile = self[j].as_ref().unwrap();
+                        tile.velocity.x as f64 * normal.0 + tile.velocity.y as f64 * normal.1
+                    })
+                    .sum::<f64>()
+                    / blockers.len().max(1) as f64;
+                let surface = self[blockers[0]].as_ref().unwrap().get_element();
+                let (new_speed, new_blocker_speed) = elastic_collide(
+                    speed,
+                    blocker_speed,
+                    mass,
+                    blocker_mass,
+                    element.loose_contact_restitution(surface),
+                );
+                for &j in &blockers {
+                    let tile = self[j].as_mut().unwrap();
+                    let change = new_blocker_speed - blocker_speed;
+                    tile.velocity.x = to_speed(tile.velocity.x as f64 + change * normal.0);
+                    tile.velocity.y = to_speed(tile.velocity.y as f64 + change * normal.1);
+                }
+                (new_speed, speed - blocker_speed)
+            }
+        };
+        let change = new_speed - speed;
+        let velocity = (
+            velocity.0 + change * normal.0,
+            velocity.1 + change * normal.1,
+        );
+
+        // Hitting something off center sets the body spinning
+        let contact_count = contacts.len().max(1) as f64;
+        let (contact_x, contact_y) = contacts.iter().fold((0.0, 0.0), |(x, y), &i| {
+            let (cx, cy) = coords(i);
+            (x + cx as f64 / contact_count, y + cy as f64 / contact_count)
+        });
+        let lever = (
+            contact_x + offset.0 as f64 / 2.0 - center_x,
+            contact_y + offset.1 as f64 / 2.0 - center_y,
+        );
+        let impulse = (mass * change * normal.0, mass * change * normal.1);
+        let torque = lever.0 * impulse.1 - lever.1 * impulse.0;
+        let inertia: f64 = tiles
+            .iter()
+            .map(|&i| {
+                let tile_mass = self[i].as_ref().unwrap().get_element().mass.max(1) as f64;
+                let (x, y) = coords(i);
+                let (dx, dy) = (x as f64 - center_x, y as f64 - center_y);
+                // Each tile is a square, not a point
+                tile_mass * (dx * dx + dy * dy + 1.0 / 6.0)
+            })
+            .sum();
+        let angular_change = torque / inertia / POSITIONS_PER_CELL as f64 / FRAC_PI_2;
+        let body = self.bodies.get_mut(&id).unwrap();
+        body.spin += angular_change;
+
+        if impact > body.strength {
+            for &i in tiles {
+                let tile = self[i].as_mut().unwrap();
+                tile.velocity.x = to_speed(velocity.0);
+                tile.velocity.y = to_speed(velocity.1);
+                self.wake(i);
+            }
+            self.break_rigid_body(id);
+        }
+        velocity
+    }
+}
+
+#[cfg(test)]
+fn rock_crate(world: &mut World) -> BodyId {
+    use crate::simple_elements::{ROCK, WALL};
+
+    crate::fill(world, &WALL, 0..=199, 199..=199);
+    crate::fill(world, &ROCK, 50..=52, 100..=102);
+    world.bind_connected(point(50, 100)).unwrap()
+}
+
+#[test]
+fn rigid_bodies_fall_as_one() {
+    let mut world = crate::create_world();
+    let id = rock_crate(&mut world);
+    crate::run(&mut world, 400);
+    let cells: Vec<usize> = (0..WORLD_SIZE as usize)
+        .filter(|&i| world[i].as_ref().is_some_and(|tile| tile.body == Some(id)))
+        .collect();
+    assert_eq!(cells.len(), 9);
+    let (x, y) = coords(cells[0]);
+    assert!(y > 100);
+    // Still in the shape of a square
+    for dx in 0..3 {
+        for dy in 0..3 {
+            assert!(cells.contains(&point(x + dx, y + dy)));
+        }
+    }
+}
+
+#[test]
+fn hard_impacts_break_rigid_bodies() {
+    let mut world = crate::create_world();
+    let id = rock_crate(&mut world);
+    world.rigid_body_mut(id).unwrap().strength = 5.0;
+    crate::run(&mut world, 2000);
+    assert!(world.rigid_body_mut(id).is_none());
+    assert!(
+        (0..WORLD_SIZE as usize).all(|i| world[i].as_ref().is_none_or(|tile| tile.body.is_none()))
+    );
+}
@@ -0,0 +1,88 @@
+use super::sleep::{CHUNKS_WIDE, CHUNK_SIZE};
+use super::{ScanOrder, World};
+use crate::world_view::CellRng;
+use crate::{point, WORLD_HEIGHT, WORLD_WIDTH};
+use rand::Rng;
+
+/// Puts `xs`, the cells of row `y` a pass visits, into the order they
+/// are visited in on the given tick
+pub(super) fn order_row(order: ScanOrder, seed: u64, tick: u64, y: i32, xs: &mut [i32]) {
+    match order {
+        ScanOrder::Forward => {}
+        ScanOrder::Alternating if tick.is_multiple_of(2) => {}
+        ScanOrder::Alternating => xs.reverse(),
+        ScanOrder::Shuffled => {
+            // Only depends on the row and the tick, so that rows are shuffled
+            // the same way whichever thread visits them
+            let row = tick
+                .wrapping_mul(WORLD_HEIGHT as u64)
+                .wrapping_add(y as u64);
+            let mut rng = CellRng::new(seed ^ row.rotate_left(32));
+            for i in (1..xs.len()).rev() {
+                xs.swap(i, rng.gen_range(0, i + 1));
+            }
+        }
+    }
+}
+
+impl World {
+    /// Starts a new tick: puts settled chunks to sleep, and moves on
+    /// to the next scan order
+    pub fn begin_tick(&mut self) {
+        self.tick = self.tick.wrapping_add(1);
+        self.update_sleep();
+    }
+
+    /// Cells of every awake chunk, a row at a time, with the rows and the
+    /// cells of each row in the order given by `settings.scan_order` for
+    /// this tick
+    pub fn scan_cells(&self) -> impl Iterator<Item = usize> + 'static {
+        let awake = self.awake.clone();
+        let (order, seed, tick) = (self.settings.scan_order, self.settings.seed, self.tick);
+        // Rows are put in order like the cells of a row, from another seed
+        let mut ys: Vec<i32> = (0..WORLD_HEIGHT).collect();
+        order_row(order, !seed, tick, 0, &mut ys);
+        ys.into_iter().flat_map(move |y| {
+            let row = (y / CHUNK_SIZE * CHUNKS_WIDE) as usize;
+            let mut xs: Vec<i32> = (0..WORLD_WIDTH)
+                .filter(|x| awake[row + (x / CHUNK_SIZE) as usize])
+                .collect();
+            order_row(order, seed, tick, y, &mut xs);
+            xs.into_iter().map(move |x| point(x, y))
+        })
+    }
+}
+
+/// How many cells to the right of the middle of the world a heap of
+/// `element` dropped there ends up centered on, on average
+#[cfg(test)]
+fn spread_bias(order: ScanOrder, element: &crate::element::Element) -> f64 {
+    use crate::simple_elements::WALL;
+
+    let mut bias = 0.0;
+    for seed in 0..2 {
+        let mut world = crate::create_world();
+        world.settings_mut().seed = seed;
+        world.settings_mut().scan_order = order;
+        crate::fill(&mut world, &WALL, 0..=199, 199..=199);
+        crate::fill(&mut world, element, 90..=109, 120..=159);
+        crate::run(&mut world, 300);
+        let xs: Vec<f64> = (0..crate::WORLD_SIZE as usize)
+            .filter(|&i| matches!(&world[i], Some(tile) if tile.element_id() == element.id().0))
+            .map(|i| crate::coords(i).0 as f64)
+            .collect();
+        let middle = (WORLD_WIDTH - 1) as f64 / 2.0;
+        bias += xs.iter().sum::<f64>() / xs.len() as f64 - middle;
+    }
+    bias / 2.0
+}
+
+#[test]
+fn alternating_and_shuffled_scans_spread_evenly_to_both_sides() {
+    use crate::simple_elements::SAND;
+
+    // Scanning left to right every tick piles sand up to the right
+    assert!(spread_bias(ScanOrder::Forward, &SAND) > 0.3);
+    assert!(spread_bias(ScanOrder::Alternating, &SAND).abs() < 0.15);
+    assert!(spread_bias(ScanOrder::Shuffled, &SAND).abs() < 0.15);
+}
@@ -0,0 +1,73 @@
+use crate::temperature::Temperature;
+
+/// What happens at the edges of the world
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Boundary {
+    /// The edges act like walls that tiles bounce off and rest against
+    Solid,
+    /// Tiles that leave the world are gone for good
+    Open,
+    /// Tiles that leave the world come back in on the opposite side
+    Wrap,
+}
+
+/// Order the cells of each row are visited in by the passes that move tiles
+/// around and run their reactions, and the order the passes that move tiles
+/// visit the rows in. Always scanning the same way makes things spread
+/// faster towards one side than the other.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ScanOrder {
+    /// Left to right and top to bottom, every tick
+    Forward,
+    /// Left to right and top to bottom, then right to left and bottom to top,
+    /// on alternate ticks
+    Alternating,
+    /// A different shuffle of the rows and of each row every tick,
+    /// drawn from the world's seed
+    Shuffled,
+}
+
+/// World-wide simulation parameters, which can be changed while the world runs
+#[derive(Clone)]
+pub struct WorldSettings {
+    /// Temperature of the environment: the air in empty cells,
+    /// and everything beyond the edge of the world
+    pub ambient_temperature: Temperature,
+    /// Thermal conductivity between tiles and the environment, from 0.0
+    /// (heat never leaves the world) to 1.0. Higher values make the world
+    /// settle back to the ambient temperature faster.
+    pub cooling_rate: f64,
+    /// Direction gravity pulls in, when there is no `gravity_center`.
+    /// Only the direction matters, not the length.
+    pub gravity_direction: (f64, f64),
+    /// Speed a falling tile gains in each gravity pass
+    pub gravity_strength: f64,
+    /// Point that everything falls towards, for planet-like scenes,
+    /// instead of falling in `gravity_direction`
+    pub gravity_center: Option<(i32, i32)>,
+    pub boundary: Boundary,
+    /// Seed for the random parts of periodic reactions and for shuffled
+    /// scans. Periodic reactions run from the same seed turn out the same,
+    /// whatever the number of `threads`. Other passes, such as gravity and
+    /// liquid flow, draw unseeded random numbers.
+    pub seed: u64,
+    /// How many threads periodic reactions are spread over
+    pub threads: usize,
+    pub scan_order: ScanOrder,
+}
+
+impl Default for WorldSettings {
+    fn default() -> Self {
+        WorldSettings {
+            ambient_temperature: Temperature::celsius(20),
+            cooling_rate: 0.01,
+            gravity_direction: (0.0, 1.0),
+            gravity_strength: 1.0,
+            gravity_center: None,
+            boundary: Boundary::Solid,
+            seed: 0,
+            scan_order: ScanOrder::Alternating,
+            threads: std::thread::available_parallelism().map_or(1, |threads| threads.get()),
+        }
+    }
+}
@@ -0,0 +1,210 @@
+use super::{sideways, Boundary, World};
+use crate::element::{PeriodicReaction, FIXED, FLUID, GRAVITY, RESTLESS};
+use crate::{coords, point, WORLD_HEIGHT, WORLD_WIDTH};
+
+/// Width and height of the square chunks that fall asleep together
+pub const CHUNK_SIZE: i32 = 16;
+pub const CHUNKS_WIDE: i32 = (WORLD_WIDTH + CHUNK_SIZE - 1) / CHUNK_SIZE;
+pub const CHUNKS_HIGH: i32 = (WORLD_HEIGHT + CHUNK_SIZE - 1) / CHUNK_SIZE;
+pub const CHUNK_COUNT: usize = (CHUNKS_WIDE * CHUNKS_HIGH) as usize;
+
+fn chunk_of(i: usize) -> (i32, i32) {
+    let (x, y) = coords(i);
+    (x / CHUNK_SIZE, y / CHUNK_SIZE)
+}
+
+impl World {
+    /// Whether the chunk holding `i` is simulated this tick
+    pub fn is_awake(&self, i: usize) -> bool {
+        let (chunk_x, chunk_y) = chunk_of(i);
+        self.awake[(chunk_x + chunk_y * CHUNKS_WIDE) as usize]
+    }
+
+    /// Wakes the chunks holding `i` and its neighbors, after something
+    /// outside the simulation of awake chunks has changed the tile there
+    pub fn wake(&mut self, i: usize) {
+        for j in std::iter::once(i).chain(self.neighbors(i)) {
+            let (chunk_x, chunk_y) = chunk_of(j);
+            let chunk = (chunk_x + chunk_y * CHUNKS_WIDE) as usize;
+            self.awake[chunk] = true;
+            self.woken[chunk] = true;
+        }
+    }
+
+    /// Wakes the whole world, such as when gravity changes
+    pub fn wake_all(&mut self) {
+        self.awake.iter_mut().for_each(|awake| *awake = true);
+        self.woken.iter_mut().for_each(|woken| *woken = true);
+    }
+
+    /// Cells of every awake chunk, in the same order as a scan of the
+    /// whole grid. Settled areas of the world are skipped over.
+    pub fn awake_cells(&self) -> impl Iterator<Item = usize> + 'static {
+        let awake = &self.awake;
+        let cells: Vec<usize> = (0..WORLD_HEIGHT)
+            .flat_map(|y| {
+                let row = (y / CHUNK_SIZE * CHUNKS_WIDE) as usize;
+                (0..CHUNKS_WIDE)
+                    .filter(move |&chunk_x| awake[row + chunk_x as usize])
+                    .flat_map(move |chunk_x| {
+                        let xs =
+                            chunk_x * CHUNK_SIZE..((chunk_x + 1) * CHUNK_SIZE).min(WORLD_WIDTH);
+                        xs.map(move |x| point(x, y))
+                    })
+            })
+            .collect();
+        cells.into_iter()
+    }
+
+    /// Notes that a periodic reaction changed the tile at `i`, so that its
+    /// chunk and the ones around it stay awake for the next pass
+    pub(super) fn mark_reacting(&mut self, i: usize) {
+        let (chunk_x, chunk_y) = chunk_of(i);
+        self.reacting[(chunk_x + chunk_y * CHUNKS_WIDE) as usize] = true;
+    }
+
+    /// Puts chunks to sleep once nothing in them is moving, reacting or
+    /// exchanging heat, and keeps the chunks around any that are still busy
+    /// awake, so that they notice anything that comes their way.
+    pub fn update_sleep(&mut self) {
+        let mut active = [false; CHUNK_COUNT];
+        for i in self.awake_cells() {
+            let (chunk_x, chunk_y) = chunk_of(i);
+            let chunk = (chunk_x + chunk_y * CHUNKS_WIDE) as usize;
+            if !active[chunk] && !self.is_settled(i) {
+                active[chunk] = true;
+            }
+        }
+        for (active, &reacting) in active.iter_mut().zip(&self.reacting) {
+            *active |= reacting;
+        }
+        let wrap = self.settings.boundary == Boundary::Wrap;
+        for chunk_y in 0..CHUNKS_HIGH {
+            for chunk_x in 0..CHUNKS_WIDE {
+                let chunk = (chunk_x + chunk_y * CHUNKS_WIDE) as usize;
+                let near_activity = (-1..=1).any(|dy| {
+                    (-1..=1).any(|dx| {
+                        let (mut x, mut y) = (chunk_x + dx, chunk_y + dy);
+                        if wrap {
+                            x = x.rem_euclid(CHUNKS_WIDE);
+                            y = y.rem_euclid(CHUNKS_HIGH);
+                        }
+                        (0..CHUNKS_WIDE).contains(&x)
+                            && (0..CHUNKS_HIGH).contains(&y)
+                            && active[(x + y * CHUNKS_WIDE) as usize]
+                    })
+                });
+                self.awake[chunk] = near_activity || self.woken[chunk];
+                self.woken[chunk] = false;
+            }
+        }
+    }
+
+    /// Whether nothing will happen to the tile at `i` until something
+    /// around it changes
+    fn is_settled(&self, i: usize) -> bool {
+        let tile = match &self[i] {
+            Some(tile) => tile,
+            None => return !self.is_windy(i),
+        };
+        // Other periodic reactions only respond to changes around the tile,
+        // and keep its chunk awake for as long as they change anything
+        let restless = match tile.get_element().periodic_reaction {
+            PeriodicReaction::DecayInto { .. } | PeriodicReaction::DecayToNothing { .. } => true,
+            PeriodicReaction::Some(_) | PeriodicReaction::None => tile.has_flag(RESTLESS),
+        };
+        if restless || !tile.velocity.is_zero() {
+            return false;
+        }
+        // Fans and hot tiles keep stirring the air around them
+        if self.convection(i).is_some() || self.is_windy(i) {
+            return false;
+        }
+        if !tile.has_flag(FIXED) {
+            if tile.has_flag(GRAVITY) && !self.has_stable_floor(i) {
+                return false;
+            }
+            if self.lift(i) > 0 && !self.has_stable_ceiling(i) {
+                return false;
+            }
+            // Liquids with room beside them may still flow, and grains slide
+            // when the ground beside them drops away
+            if let Some(down) = self.down_step(i) {
+                let angle = tile.angle_of_repose();
+                let spreads = [-1, 1].iter().any(|&direction| {
+                    if tile.has_flag(FLUID) {
+                        self.empty_cell(i, sideways(down, direction)).is_some()
+                    } else {
+                        angle > 0.0 && self.slide_destination(i, down, direction, angle).is_some()
+                    }
+                });
+                if spreads {
+                    return false;
+                }
+            }
+        }
+        // Anything that is still warming up or cooling down
+        let ambient_temperature = self.settings.ambient_temperature;
+        let cooling = self.settings.cooling_rate > 0.0;
+        let mut neighbors = 0;
+        for j in self.neighbors(i) {
+            neighbors += 1;
+            match &self[j] {
+                Some(neighbor) if neighbor.temperature != tile.temperature => return false,
+                None if cooling && tile.temperature != ambient_temperature => return false,
+                _ => {}
+            }
+        }
+        neighbors == 8 || !cooling || tile.temperature == ambient_temperature
+    }
+}
+
+#[test]
+fn settled_piles_fall_asleep_until_woken() {
+    use crate::simple_elements::{SAND, WALL};
+
+    let mut world = crate::create_world();
+    crate::fill(&mut world, &WALL, 0..=199, 199..=199);
+    crate::fill(&mut world, &SAND, 20..=60, 195..=198);
+    crate::run(&mut world, 1000);
+    world.update_sleep();
+    assert!(!world.is_awake(point(150, 50)));
+    assert!(!world.is_awake(point(25, 190)));
+    assert!(world.awake_cells().next().is_none());
+
+    // Digging out the bottom of the pile wakes it up again
+    world[point(25, 198)] = None;
+    world.wake(point(25, 198));
+    crate::run(&mut world, 100);
+    assert!(world[point(25, 198)].is_some());
+}
+
+#[test]
+fn reactions_keep_their_chunks_awake_while_they_change_things() {
+    use crate::element::Channel;
+    use crate::metal::{CHARGED_HEAD, METAL};
+
+    let mut world = crate::create_world();
+    crate::fill(&mut world, &METAL, 10..=150, 100..=100);
+    crate::run(&mut world, 100);
+    world.update_sleep();
+    assert!(!world.is_awake(point(140, 100)));
+
+    // A charge set off at one end of the wire runs all the way along it
+    let start = point(10, 100);
+    let tile = world[start].as_mut().unwrap();
+    tile.set_channel(Channel::Charge, CHARGED_HEAD);
+    tile.save_state();
+    world.wake(start);
+    let mut reached_the_end = false;
+    for _ in 0..200 {
+        crate::run(&mut world, 3);
+        reached_the_end |= world[point(140, 100)].as_ref().unwrap().is_charged_metal();
+    }
+    assert!(reached_the_end);
+
+    // And the wire goes back to sleep once it has passed
+    crate::run(&mut world, 100);
+    world.update_sleep();
+    assert!(!world.is_awake(point(140, 100)));
+}
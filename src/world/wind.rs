@@ -0,0 +1,206 @@
+use super::World;
+use crate::element::FIXED;
+use crate::tile::Speed;
+use crate::{coords, offset};
+use rand::Rng;
+
+/// Fraction of the wind in a cell that is left after each air flow pass
+const AIR_FLOW_DECAY: f64 = 0.8;
+/// Wind rising off a hot tile, for each degree it is hotter than the environment
+const CONVECTION_RATE: f64 = 0.02;
+/// Fastest the wind can blow, the same as the fastest a tile can move
+const WIND_LIMIT: f64 = Speed::MAX as f64;
+
+/// Velocity of the air in a cell, in the same units as tile velocities
+#[derive(Clone, Copy, Default, PartialEq, Debug)]
+pub struct Wind {
+    pub x: f64,
+    pub y: f64,
+}
+
+/// How much the wind speeds a tile up in one pass, which is less the heavier
+/// the tile is. Tiles already moving with the wind at least as fast as it
+/// blows aren't pushed any further.
+fn wind_push(wind: f64, velocity: Speed, mass: f64, dither: f64) -> Speed {
+    let headroom = wind - velocity as f64;
+    if headroom * wind <= 0.0 {
+        return 0;
+    }
+    // Round up or down at random, so gentle winds still move light tiles
+    let push = (wind / mass + dither).floor();
+    if wind > 0.0 {
+        push.min(headroom) as Speed
+    } else {
+        push.max(headroom) as Speed
+    }
+}
+
+impl World {
+    pub fn wind_at(&self, i: usize) -> Wind {
+        self.wind[i]
+    }
+
+    /// Adds a gust to the wind at `i`, which then spreads out through the air
+    pub fn blow(&mut self, i: usize, gust: Wind) {
+        self.wind[i].x += gust.x;
+        self.wind[i].y += gust.y;
+    }
+
+    /// Spreads the wind through open space, adds wind from fans and the air
+    /// rising off hot tiles, and lets the wind carry tiles along with it.
+    /// Lighter tiles catch up with the wind faster. The air in sleeping
+    /// chunks is left as it is.
+    pub fn apply_air_flow(&mut self) {
+        let cells: Vec<usize> = self.awake_cells().collect();
+        let mut next_wind = std::mem::take(&mut self.next_wind);
+        next_wind.copy_from_slice(&self.wind);
+        for &i in &cells {
+            next_wind[i] = self.spread_wind(i);
+        }
+        for &i in &cells {
+            if let Some((j, updraft)) = self.convection(i) {
+                let wind = &mut next_wind[j];
+                wind.x = (wind.x + updraft.x).clamp(-WIND_LIMIT, WIND_LIMIT);
+                wind.y = (wind.y + updraft.y).clamp(-WIND_LIMIT, WIND_LIMIT);
+            }
+        }
+        self.next_wind = std::mem::replace(&mut self.wind, next_wind);
+
+        let mut rng = rand::thread_rng();
+        for &i in &cells {
+            let wind = self.wind[i];
+            if let Some(tile) = &mut self[i] {
+                if tile.has_flag(FIXED) {
+                    continue;
+                }
+                let mass = tile.get_element().mass.max(1) as f64;
+                let push_x = wind_push(wind.x, tile.velocity.x, mass, rng.gen());
+                let push_y = wind_push(wind.y, tile.velocity.y, mass, rng.gen());
+                tile.velocity.x = tile.velocity.x.saturating_add(push_x);
+                tile.velocity.y = tile.velocity.y.saturating_add(push_y);
+                if push_x != 0 || push_y != 0 {
+                    self.wake(i);
+                }
+            }
+        }
+    }
+
+    /// The open cell the tile at `i` blows air into, and the wind it adds
+    /// there each pass, if it is a fan or is hotter than the environment
+    pub(super) fn convection(&self, i: usize) -> Option<(usize, Wind)> {
+        let tile = self[i].as_ref()?;
+        let heat = (tile.temperature - self.settings.ambient_temperature).max(0) as f64;
+        let updraft = tile.get_element().updraft + CONVECTION_RATE * heat;
+        if updraft <= 0.0 {
+            return None;
+        }
+        // Hot air rises against gravity, or straight up where there is none
+        let (down_x, down_y) = match self.gravity_at(i) {
+            (x, y) if x == 0.0 && y == 0.0 => (0.0, 1.0),
+            down => down,
+        };
+        let (step_x, step_y) = self.down_step(i).unwrap_or((0, 1));
+        let j = offset(i, (-step_x, -step_y), self.settings.boundary)
+            .filter(|&j| !self.blocks_wind(j))?;
+        Some((
+            j,
+            Wind {
+                x: -down_x * updraft,
+                y: -down_y * updraft,
+            },
+        ))
+    }
+
+    /// Whether the wind at `i` is still strong enough to push tiles around
+    pub(super) fn is_windy(&self, i: usize) -> bool {
+        let wind = self.wind[i];
+        wind.x.abs().max(wind.y.abs()) >= 1.0
+    }
+
+    fn blocks_wind(&self, i: usize) -> bool {
+        self[i].as_ref().is_some_and(|tile| tile.has_flag(FIXED))
+    }
+
+    /// Wind at `i` after the next pass: the average of the wind around it,
+    /// ignoring anything solid, which the wind can't blow through.
+    fn spread_wind(&self, i: usize) -> Wind {
+        if self.blocks_wind(i) {
+            return Wind::default();
+        }
+        let (x, y) = coords(i);
+        let mut total = self.wind[i];
+        let mut cells = 1.0;
+        for &(dx, dy) in &[(0, -1), (-1, 0), (1, 0), (0, 1)] {
+            match self.cell_at(x + dx, y + dy) {
+                Some(j) if !self.blocks_wind(j) => {
+                    total.x += self.wind[j].x;
+                    total.y += self.wind[j].y;
+                    cells += 1.0;
+                }
+                _ => {}
+            }
+        }
+        Wind {
+            x: AIR_FLOW_DECAY * total.x / cells,
+            y: AIR_FLOW_DECAY * total.y / cells,
+        }
+    }
+}
+
+#[test]
+fn air_rises_above_hot_tiles() {
+    use crate::simple_elements::WALL;
+    use crate::{point, ElementState, Temperature, Tile};
+
+    let mut world = crate::create_world();
+    world[point(50, 50)] = Some(Tile::stationary(
+        ElementState::default(WALL.id()),
+        Temperature::celsius(500),
+    ));
+    world.apply_air_flow();
+    assert!(world.wind_at(point(50, 49)).y < 0.0);
+    assert_eq!(world.wind_at(point(50, 51)), Wind::default());
+}
+
+#[test]
+fn wind_carries_light_tiles_faster_than_heavy_ones() {
+    use crate::gas::GAS;
+    use crate::simple_elements::ROCK;
+    use crate::{point, ElementState, Temperature, Tile};
+
+    let mut world = crate::create_world();
+    for x in 40..60 {
+        for y in 40..60 {
+            world.blow(point(x, y), Wind { x: 60.0, y: 0.0 });
+        }
+    }
+    world[point(45, 50)] = Some(Tile::stationary(
+        ElementState::default(GAS.id()),
+        Temperature::celsius(20),
+    ));
+    world[point(55, 50)] = Some(Tile::stationary(
+        ElementState::default(ROCK.id()),
+        Temperature::celsius(20),
+    ));
+    world.apply_air_flow();
+    let gas = world[point(45, 50)].as_ref().unwrap().velocity.x;
+    let rock = world[point(55, 50)].as_ref().unwrap().velocity.x;
+    assert!(rock >= 0);
+    assert!(gas > rock + 5);
+}
+
+#[test]
+fn hot_air_rises_against_sideways_gravity() {
+    use crate::simple_elements::WALL;
+    use crate::{point, ElementState, Temperature, Tile};
+
+    let mut world = crate::create_world();
+    world.settings_mut().gravity_direction = (1.0, 0.0);
+    world[point(50, 50)] = Some(Tile::stationary(
+        ElementState::default(WALL.id()),
+        Temperature::celsius(500),
+    ));
+    world.apply_air_flow();
+    assert!(world.wind_at(point(49, 50)).x < 0.0);
+    assert_eq!(world.wind_at(point(50, 49)).y, 0.0);
+}
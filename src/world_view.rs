@@ -1,4 +1,7 @@
-use crate::{raw_neighbors, WORLD_WIDTH};
+use crate::world::{Boundary, FieldId, Wind};
+use crate::{cell_within, coords, neighbors_within, WORLD_HEIGHT, WORLD_WIDTH};
+use itertools::iproduct;
+use rand::{Error, Rng, RngCore};
 use std::ops::{Index, IndexMut};
 
 #[derive(Clone, Copy, Debug)]
@@ -10,66 +13,229 @@ impl NeighborhoodIndex {
     }
 }
 
+/// Random numbers for the reaction of a single cell, which only depend on
+/// the seed they start from, so reactions come out the same however the
+/// world is split up between threads
+pub struct CellRng(u64);
+
+impl CellRng {
+    pub fn new(seed: u64) -> CellRng {
+        CellRng(seed)
+    }
+}
+
+impl RngCore for CellRng {
+    fn next_u32(&mut self) -> u32 {
+        (self.next_u64() >> 32) as u32
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        // splitmix64
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        for chunk in dest.chunks_mut(8) {
+            let bytes = self.next_u64().to_le_bytes();
+            chunk.copy_from_slice(&bytes[..chunk.len()]);
+        }
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), Error> {
+        self.fill_bytes(dest);
+        Ok(())
+    }
+}
+
+/// Index into a window of the world starting at `origin`, in rows `width`
+/// cells wide, of the cell `(dx, dy)` from `center`, or `None` if that
+/// is outside the world
+pub(crate) fn window_cell(
+    center: (i32, i32),
+    origin: (i32, i32),
+    width: i32,
+    boundary: Boundary,
+    (dx, dy): (i32, i32),
+) -> Option<usize> {
+    let (x, y) = (center.0 + dx, center.1 + dy);
+    cell_within(x, y, boundary)?;
+    let local_x = (x - origin.0).rem_euclid(WORLD_WIDTH);
+    let local_y = (y - origin.1).rem_euclid(WORLD_HEIGHT);
+    Some((local_x + local_y * width) as usize)
+}
+
+/// A tile's view of the cells around it, in either the whole world or
+/// a window onto part of it
 pub struct NeighborhoodView<'a, T> {
     slice: &'a mut [T],
-    index: usize,
+    /// Values of each of the world's scalar fields, laid out like `slice`
+    fields: &'a mut [Vec<f64>],
+    /// World coordinates of the cell in the middle of the view
+    center: (i32, i32),
+    /// World coordinates of the first cell of `slice`, which holds
+    /// rows `width` cells wide
+    origin: (i32, i32),
+    width: i32,
+    boundary: Boundary,
+    rng: CellRng,
 }
 
 impl<'a, T> NeighborhoodView<'a, T> {
-    pub fn new(slice: &'a mut [T], index: usize) -> Self {
-        NeighborhoodView { slice, index }
+    /// A view around `index` of `slice`, which holds the whole world
+    pub fn new(
+        slice: &'a mut [T],
+        fields: &'a mut [Vec<f64>],
+        index: usize,
+        boundary: Boundary,
+    ) -> Self {
+        NeighborhoodView {
+            slice,
+            fields,
+            center: coords(index),
+            origin: (0, 0),
+            width: WORLD_WIDTH,
+            boundary,
+            rng: CellRng::new(rand::thread_rng().gen()),
+        }
     }
 
-    pub fn neighbors(&self) -> impl Iterator<Item = NeighborhoodIndex> {
-        raw_neighbors(self.index).map(NeighborhoodIndex)
+    /// A view around `center` of `slice`, which holds the part of the world
+    /// starting at `origin` in rows `width` cells wide
+    pub(crate) fn within(
+        slice: &'a mut [T],
+        fields: &'a mut [Vec<f64>],
+        center: (i32, i32),
+        origin: (i32, i32),
+        width: i32,
+        boundary: Boundary,
+        rng: CellRng,
+    ) -> Self {
+        NeighborhoodView {
+            slice,
+            fields,
+            center,
+            origin,
+            width,
+            boundary,
+            rng,
+        }
     }
 
-    pub fn above(&mut self) -> &mut T {
-        &mut self.slice[self.index - WORLD_WIDTH as usize]
+    /// Index into `slice` of the cell `(dx, dy)` from the center,
+    /// or `None` if that is outside the world
+    fn cell(&self, step: (i32, i32)) -> Option<usize> {
+        window_cell(self.center, self.origin, self.width, self.boundary, step)
     }
 
-    pub fn below(&mut self) -> &mut T {
-        &mut self.slice[self.index + WORLD_WIDTH as usize]
+    pub fn neighbors(&self) -> impl Iterator<Item = NeighborhoodIndex> + 'static {
+        let (center, origin, width, boundary) =
+            (self.center, self.origin, self.width, self.boundary);
+        iproduct!(-1i32..=1i32, -1i32..=1i32)
+            .filter(|&step| step != (0, 0))
+            .filter_map(move |step| window_cell(center, origin, width, boundary, step))
+            .map(NeighborhoodIndex)
+    }
+
+    /// The cell above, or `None` at the top edge of a world that doesn't wrap
+    pub fn above(&mut self) -> Option<&mut T> {
+        let i = self.cell((0, -1))?;
+        Some(&mut self.slice[i])
+    }
+
+    /// The cell below, or `None` at the bottom edge of a world that doesn't wrap
+    pub fn below(&mut self) -> Option<&mut T> {
+        let i = self.cell((0, 1))?;
+        Some(&mut self.slice[i])
     }
 
     pub fn for_each_neighbor(&mut self, mut f: impl FnMut(&mut T)) {
-        for i in raw_neighbors(self.index) {
-            f(&mut self.slice[i])
+        for i in self.neighbors() {
+            f(&mut self.slice[i.0])
         }
     }
+
+    /// Random numbers for reactions, which are the same each time the world
+    /// is run from the same seed
+    pub fn rng(&mut self) -> &mut CellRng {
+        &mut self.rng
+    }
+
+    /// Value of `field` in the middle of the view
+    pub fn field(&self, field: FieldId) -> f64 {
+        self.fields[field][self.cell((0, 0)).unwrap()]
+    }
+
+    pub fn field_mut(&mut self, field: FieldId) -> &mut f64 {
+        let i = self.cell((0, 0)).unwrap();
+        &mut self.fields[field][i]
+    }
+
+    /// Value of `field` in one of the neighboring cells
+    pub fn neighbor_field(&self, field: FieldId, index: NeighborhoodIndex) -> f64 {
+        self.fields[field][index.0]
+    }
+
+    pub fn neighbor_field_mut(&mut self, field: FieldId, index: NeighborhoodIndex) -> &mut f64 {
+        &mut self.fields[field][index.0]
+    }
 }
 
 pub struct CollisionView<'a, T> {
     slice: &'a mut [T],
+    fields: &'a mut [Vec<f64>],
+    wind: &'a mut [Wind],
     /// Index of whichever particle has lower element id
     first_index: usize,
     /// Index of whichever particle has higher element id
     second_index: usize,
+    boundary: Boundary,
 }
 
 impl<'a, T> CollisionView<'a, T> {
-    pub fn new(slice: &'a mut [T], first_index: usize, second_index: usize) -> Self {
+    pub fn new(
+        slice: &'a mut [T],
+        fields: &'a mut [Vec<f64>],
+        wind: &'a mut [Wind],
+        first_index: usize,
+        second_index: usize,
+        boundary: Boundary,
+    ) -> Self {
         CollisionView {
             slice,
+            fields,
+            wind,
             first_index,
             second_index,
+            boundary,
         }
     }
 
     /// A neighborhood view for the first particle
-    pub fn first(&mut self) -> NeighborhoodView<T> {
-        NeighborhoodView::new(self.slice, self.first_index)
+    pub fn first(&mut self) -> NeighborhoodView<'_, T> {
+        NeighborhoodView::new(self.slice, self.fields, self.first_index, self.boundary)
     }
 
     /// A neighborhood view for the second particle
-    pub fn second(&mut self) -> NeighborhoodView<T> {
-        NeighborhoodView::new(self.slice, self.second_index)
+    pub fn second(&mut self) -> NeighborhoodView<'_, T> {
+        NeighborhoodView::new(self.slice, self.fields, self.second_index, self.boundary)
+    }
+
+    /// Adds a gust to the wind at the given index, such as the blast of an explosion
+    pub fn blow(&mut self, index: NeighborhoodIndex, gust: Wind) {
+        if let Some(wind) = self.wind.get_mut(index.0) {
+            wind.x += gust.x;
+            wind.y += gust.y;
+        }
     }
 
     /// Applies the given function to all neighboring indexes of the first particle,
     /// excluding the first and second particles themselves.
     pub fn for_neighbors_of_first(&mut self, mut f: impl FnMut(&mut T)) {
-        for i in raw_neighbors(self.first_index) {
+        for i in neighbors_within(self.first_index, self.boundary) {
             if i != self.second_index {
                 f(&mut self.slice[i]);
             }
@@ -79,7 +245,7 @@ impl<'a, T> CollisionView<'a, T> {
     /// Applies the given function to all neighboring indexes of the second particle,
     /// excluding the first and second particles themselves.
     pub fn for_neighbors_of_second(&mut self, mut f: impl FnMut(&mut T)) {
-        for i in raw_neighbors(self.second_index) {
+        for i in neighbors_within(self.second_index, self.boundary) {
             if i != self.first_index {
                 f(&mut self.slice[i]);
             }
@@ -112,3 +278,17 @@ impl<'a, T> IndexMut<NeighborhoodIndex> for CollisionView<'a, T> {
         &mut self.slice[index.0]
     }
 }
+
+#[test]
+fn neighborhoods_stop_at_the_edge_unless_the_world_wraps() {
+    let mut cells = vec![0; crate::WORLD_SIZE as usize];
+    let corner = 0;
+    let mut view = NeighborhoodView::new(&mut cells, &mut [], corner, Boundary::Solid);
+    assert!(view.above().is_none());
+    assert_eq!(view.neighbors().count(), 3);
+    let mut view = NeighborhoodView::new(&mut cells, &mut [], corner, Boundary::Wrap);
+    *view.above().unwrap() = 1;
+    assert_eq!(view.neighbors().count(), 8);
+    view.for_each_neighbor(|cell| *cell += 1);
+    assert_eq!(cells.iter().sum::<i32>(), 9);
+}